@@ -0,0 +1,114 @@
+#[cfg(test)]
+mod test;
+
+use super::util;
+
+const DEFAULT_PROJECT: &str = "default";
+
+pub const USAGE: &str = "\
+usage: turtle [project] [options]
+
+options:
+    -p, --project {name}       Project to work on, default is [ default ].
+    -c, --command {text}       Run an instruction and exit, can be repeated.
+    -s, --script {file}        Run all instructions in a file and exit.
+        --config-dir {dir}     Directory of config files, default is ~/.turtle.
+        --no-clear             Don't clear the screen when turtle shell starts.
+        --dry-run              Print instructions instead of running them.
+        --verbose              Show every command before running it.
+        --list-projects        List all projects in the config directory.
+    -v, --version              Show version of turtle.
+    -h, --help                 Show this message.";
+
+#[derive(Debug, PartialEq)]
+pub struct Arguments {
+    pub project: String,
+    pub config_dir: String,
+    pub commands: Vec<String>,
+    pub script: Option<String>,
+    pub no_clear: bool,
+    pub dry_run: bool,
+    pub verbose: bool,
+    pub list_projects: bool,
+    pub version: bool,
+    pub help: bool,
+}
+
+impl Arguments {
+    pub fn new() -> Self {
+        return Self {
+            project: String::from(DEFAULT_PROJECT),
+            config_dir: util::default_config_directory(),
+            commands: Vec::new(),
+            script: None,
+            no_clear: false,
+            dry_run: false,
+            verbose: false,
+            list_projects: false,
+            version: false,
+            help: false,
+        };
+    }
+
+    // Parse command line arguments, not including the program name
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let mut result = Self::new();
+        let mut has_project = false;
+        let mut iter = args.iter();
+
+        while let Some(arg) = iter.next() {
+            let (flag, inline) = match arg.find('=') {
+                Some(pos) if arg.starts_with("--") => {
+                    (&arg[..pos], Some(arg[pos + 1..].to_owned()))
+                }
+                _ => (&arg[..], None),
+            };
+
+            let mut value = || -> Result<String, String> {
+                if let Some(value) = &inline {
+                    return Ok(value.to_owned());
+                }
+                match iter.next() {
+                    Some(value) => return Ok(value.to_owned()),
+                    None => return Err(format!("missing value of [ {} ]", flag)),
+                }
+            };
+
+            match flag {
+                "-p" | "--project" => {
+                    result.project = value()?.to_lowercase();
+                    has_project = true;
+                }
+                "-c" | "--command" => result.commands.push(value()?),
+                "-s" | "--script" => result.script = Some(value()?),
+                "--config-dir" => {
+                    result.config_dir = util::normalize_path(&value()?);
+                }
+                "--no-clear" => result.no_clear = true,
+                "--dry-run" => result.dry_run = true,
+                "--verbose" => result.verbose = true,
+                "--list-projects" => result.list_projects = true,
+                "-v" | "--version" => result.version = true,
+                "-h" | "--help" => result.help = true,
+                _ => {
+                    if flag.starts_with('-') {
+                        return Err(format!("unknown option [ {} ]", flag));
+                    }
+                    if has_project {
+                        return Err(format!("unexpected argument [ {} ]", arg));
+                    }
+                    result.project = arg.to_lowercase();
+                    has_project = true;
+                }
+            }
+        }
+
+        return Ok(result);
+    }
+
+    // Return true if turtle should run instructions and exit instead of
+    // starting an interactive shell
+    pub fn is_batch(&self) -> bool {
+        return !self.commands.is_empty() || self.script.is_some();
+    }
+}
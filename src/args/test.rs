@@ -0,0 +1,72 @@
+use super::*;
+
+fn parse(args: &[&str]) -> Result<Arguments, String> {
+    let args: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+    return Arguments::parse(&args);
+}
+
+#[test]
+fn test_parse_default() {
+    let args = parse(&[]).expect("cannot parse empty arguments");
+    assert_eq!(args, Arguments::new());
+    assert_eq!(args.project, "default");
+    assert_eq!(args.config_dir, util::default_config_directory());
+    assert!(!args.is_batch());
+}
+
+#[test]
+fn test_parse_project() {
+    let args = parse(&["Forest"]).unwrap();
+    assert_eq!(args.project, "forest");
+
+    let args = parse(&["--project", "forest"]).unwrap();
+    assert_eq!(args.project, "forest");
+
+    let args = parse(&["--project=forest"]).unwrap();
+    assert_eq!(args.project, "forest");
+}
+
+#[test]
+fn test_parse_options() {
+    let args = parse(&[
+        "forest",
+        "--config-dir",
+        "~/configs",
+        "-c",
+        "build lotus",
+        "--command=test lotus",
+        "--script",
+        "ci.turtle",
+        "--no-clear",
+        "--dry-run",
+        "--verbose",
+        "--list-projects",
+        "--version",
+        "--help",
+    ])
+    .unwrap();
+
+    assert_eq!(args.project, "forest");
+    assert_eq!(args.config_dir, util::normalize_path("~/configs"));
+    assert_eq!(args.commands, vec!["build lotus", "test lotus"]);
+    assert_eq!(args.script, Some(String::from("ci.turtle")));
+    assert!(args.no_clear);
+    assert!(args.dry_run);
+    assert!(args.verbose);
+    assert!(args.list_projects);
+    assert!(args.version);
+    assert!(args.help);
+    assert!(args.is_batch());
+}
+
+#[test]
+fn test_parse_errors() {
+    let err = parse(&["--config-dir"]).unwrap_err();
+    assert_eq!(err, "missing value of [ --config-dir ]");
+
+    let err = parse(&["--unknown"]).unwrap_err();
+    assert_eq!(err, "unknown option [ --unknown ]");
+
+    let err = parse(&["forest", "lotus"]).unwrap_err();
+    assert_eq!(err, "unexpected argument [ lotus ]");
+}
@@ -2,6 +2,7 @@
 mod test;
 
 use super::cmd::Command;
+use super::util;
 
 const INSTALL_BREW: &str = "/usr/bin/ruby -e \"$(curl -fsSL \
                             https://raw.githubusercontent.com/Homebrew/install/master/install)\"";
@@ -11,6 +12,8 @@ pub fn install_brew() -> Command {
 }
 
 pub fn install_packages(names: &[&str]) -> Command {
+    let names: Vec<String> =
+        names.iter().map(|name| util::quote_argument(name)).collect();
     let raw = format!("brew install {}", names.join(" "));
     return Command::basic_show(&raw);
 }
@@ -2,9 +2,74 @@
 mod test;
 
 use std::cmp::PartialEq;
+use std::collections::BTreeMap;
 use std::fmt;
+use std::time::Duration;
 
-type ExecFn = Box<dyn Fn(&str) -> (bool, String)>;
+type ExecFn = Box<dyn Fn(&str) -> CommandResult + Send + Sync>;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ErrorKind {
+    // Cannot change to the directory of the command
+    Directory,
+    // Cannot start or wait for the child process
+    Execute,
+    // The child process exited with a non-zero status
+    Exit,
+    // The output of the command is not as expected
+    Output,
+    // The command took longer than its timeout and was killed
+    Timeout,
+    // The command was interrupted by the user with ctrl-c
+    Cancelled,
+    // The instruction is invalid, such as an unknown command or a missing
+    // argument
+    Invalid,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct CommandResult {
+    pub code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    pub duration: Duration,
+    pub error: Option<ErrorKind>,
+}
+
+impl CommandResult {
+    pub fn ok(stdout: &str) -> Self {
+        return Self {
+            code: Some(0),
+            stdout: String::from(stdout),
+            stderr: String::new(),
+            duration: Duration::from_secs(0),
+            error: None,
+        };
+    }
+
+    pub fn fail(error: ErrorKind, message: &str) -> Self {
+        return Self {
+            code: None,
+            stdout: String::new(),
+            stderr: String::from(message),
+            duration: Duration::from_secs(0),
+            error: Some(error),
+        };
+    }
+
+    pub fn success(&self) -> bool {
+        return self.error.is_none();
+    }
+
+    // Exit code to report to the calling process
+    pub fn exit_code(&self) -> i32 {
+        match (self.error, self.code) {
+            (None, _) => return 0,
+            (Some(_), Some(code)) if code != 0 => return code,
+            _ => return 1,
+        }
+    }
+}
 
 pub struct Command {
     pub raw: String,
@@ -14,6 +79,12 @@ pub struct Command {
     pub pipe: bool,
     pub then: Option<ExecFn>,
     pub back: bool,
+    pub label: String,
+    pub timeout: Option<Duration>,
+    // Environment variables to set, or to remove when their value is None
+    pub env: BTreeMap<String, Option<String>>,
+    // Message printed by an echo command, shown as is in dry-run mode
+    pub message: Option<String>,
 }
 
 impl Command {
@@ -34,9 +105,36 @@ impl Command {
             pipe: pipe,
             then: then,
             back: back,
+            label: String::new(),
+            timeout: None,
+            env: BTreeMap::new(),
+            message: None,
         };
     }
 
+    // Name of the service or repository this command works on, used to
+    // prefix its output and to report its result
+    pub fn labeled(mut self, label: &str) -> Self {
+        self.label = String::from(label);
+        return self;
+    }
+
+    // Kill the command if it is still running after the number of seconds
+    pub fn with_timeout(mut self, seconds: Option<u64>) -> Self {
+        self.timeout = seconds.map(Duration::from_secs);
+        return self;
+    }
+
+    // Add environment variables that the command doesn't define by itself
+    pub fn with_env(mut self, env: &BTreeMap<String, Option<String>>) -> Self {
+        for (key, value) in env {
+            if !self.env.contains_key(key) {
+                self.env.insert(key.to_owned(), value.clone());
+            }
+        }
+        return self;
+    }
+
     pub fn basic_hide(raw: &str) -> Self {
         return Self::new(raw, "", false, false, false, None, true);
     }
@@ -46,8 +144,62 @@ impl Command {
     }
 
     pub fn echo(message: &str) -> Self {
-        let raw = format!("echo \"{}\"", message);
-        return Self::basic_hide(&raw);
+        let escaped = message
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('$', "\\$")
+            .replace('`', "\\`");
+        let raw = format!("echo \"{}\"", escaped);
+        let mut command = Self::basic_hide(&raw);
+        command.message = Some(String::from(message));
+        return command;
+    }
+}
+
+impl Command {
+    // Describe what running this command would do, used in dry-run mode
+    pub fn render(&self) -> String {
+        if let Some(message) = &self.message {
+            return message.to_owned();
+        }
+
+        let mut lines: Vec<String> = Vec::new();
+
+        if !self.raw.is_empty() {
+            lines.push(format!("$ {}", self.raw));
+        }
+
+        if !self.dir.is_empty() {
+            let back = if self.back {
+                "then back to previous directory"
+            } else {
+                "and stay there"
+            };
+            lines.push(format!("    in [ {} ] {}", self.dir, back));
+        }
+
+        if !self.env.is_empty() {
+            let vars: Vec<String> = self
+                .env
+                .iter()
+                .map(|(key, value)| match value {
+                    Some(value) => format!("{}={}", key, value),
+                    None => format!("{} unset", key),
+                })
+                .collect();
+            lines.push(format!("    with env {}", vars.join(", ")));
+        }
+
+        if let Some(timeout) = self.timeout {
+            let seconds = timeout.as_secs();
+            lines.push(format!("    time out after {} seconds", seconds));
+        }
+
+        if self.then.is_some() {
+            lines.push(String::from("    then process its output"));
+        }
+
+        return lines.join("\n");
     }
 }
 
@@ -60,6 +212,10 @@ impl PartialEq for Command {
             || self.silent != other.silent
             || self.pipe != other.pipe
             || self.back != other.back
+            || self.label != other.label
+            || self.timeout != other.timeout
+            || self.env != other.env
+            || self.message != other.message
         {
             return false;
         }
@@ -75,8 +231,8 @@ impl fmt::Debug for Command {
         };
         return write!(
             f,
-            "Command {{ raw: \"{}\", dir: \"{}\", show: {}, silent: {}, pipe: {}, exec: {} }}",
-            self.raw, self.dir, self.show, self.silent, self.pipe, exec,
+            "Command {{ raw: \"{}\", dir: \"{}\", show: {}, silent: {}, pipe: {}, exec: {}, label: \"{}\", timeout: {:?}, env: {:?} }}",
+            self.raw, self.dir, self.show, self.silent, self.pipe, exec, self.label, self.timeout, self.env,
         );
     }
 }
@@ -7,9 +7,9 @@ fn test_new_command() {
     let show = true;
     let silent = false;
     let pipe = true;
-    let exec = |name: &str| -> (bool, String) {
+    let exec = |name: &str| -> CommandResult {
         let output = format!("bonjour {}!", name);
-        return (true, output);
+        return CommandResult::ok(&output);
     };
     let back = true;
 
@@ -23,9 +23,53 @@ fn test_new_command() {
     assert_eq!(command.back, back);
 
     let then = &command.then.expect("then is None");
-    let (success, output) = then("rust");
-    assert!(success);
-    assert_eq!(output, "bonjour rust!");
+    let result = then("rust");
+    assert!(result.success());
+    assert_eq!(result.stdout, "bonjour rust!");
+}
+
+#[test]
+fn test_labeled_command() {
+    let command = Command::basic_show("cargo build").labeled("lotus");
+    assert_eq!(command.label, "lotus");
+    assert_ne!(command, Command::basic_show("cargo build"));
+}
+
+#[test]
+fn test_command_with_timeout() {
+    let command = Command::basic_show("git pull").with_timeout(Some(30));
+    assert_eq!(command.timeout, Some(Duration::from_secs(30)));
+    assert_ne!(command, Command::basic_show("git pull"));
+
+    let command = Command::basic_show("git pull").with_timeout(None);
+    assert_eq!(command.timeout, None);
+    assert_eq!(command.render(), "$ git pull");
+
+    let command = Command::basic_show("git pull").with_timeout(Some(5));
+    assert_eq!(command.render(), "$ git pull\n    time out after 5 seconds");
+}
+
+#[test]
+fn test_command_with_env() {
+    let mut env = BTreeMap::new();
+    env.insert(String::from("AWS_PROFILE"), Some(String::from("dev")));
+    env.insert(String::from("RUST_LOG"), None);
+
+    let command = Command::basic_show("aws s3 ls").with_env(&env);
+    assert_eq!(command.env, env);
+    assert_ne!(command, Command::basic_show("aws s3 ls"));
+
+    let expect = "$ aws s3 ls\n    with env AWS_PROFILE=dev, RUST_LOG unset";
+    assert_eq!(command.render(), expect);
+
+    let mut session = BTreeMap::new();
+    session.insert(String::from("AWS_PROFILE"), Some(String::from("prod")));
+    session.insert(String::from("REGION"), Some(String::from("eu")));
+
+    let command = command.with_env(&session);
+    assert_eq!(command.env["AWS_PROFILE"], Some(String::from("dev")));
+    assert_eq!(command.env["REGION"], Some(String::from("eu")));
+    assert_eq!(command.env.len(), 3);
 }
 
 #[test]
@@ -53,3 +97,67 @@ fn test_echo_command() {
     assert!(command.then.is_none());
     assert!(command.back);
 }
+
+#[test]
+fn test_echo_command_escape() {
+    let command = Command::echo("run `ls` with \"$HOME\" and \\n");
+    let expect = r#"echo "run \`ls\` with \"\$HOME\" and \\n""#;
+    assert_eq!(command.raw, expect);
+}
+
+#[test]
+fn test_render_echo_command() {
+    let mut env = BTreeMap::new();
+    env.insert(String::from("AWS_PROFILE"), Some(String::from("dev")));
+
+    let message = "--> unknown group [ \"abc\" ]";
+    let command = Command::echo(message).with_env(&env);
+    assert_eq!(command.message, Some(String::from(message)));
+    assert_eq!(command.render(), message);
+}
+
+#[test]
+fn test_render_command() {
+    let command = Command::basic_show("ls -la");
+    assert_eq!(command.render(), "$ ls -la");
+
+    let command = Command::new("", "/tmp", false, false, false, None, false);
+    assert_eq!(command.render(), "    in [ /tmp ] and stay there");
+
+    let exec = |s: &str| -> CommandResult { CommandResult::ok(s) };
+    let command = Command::new(
+        "cargo build",
+        "/tmp",
+        true,
+        false,
+        true,
+        Some(Box::new(exec)),
+        true,
+    );
+    let expect = "$ cargo build\n    \
+                  in [ /tmp ] then back to previous directory\n    \
+                  then process its output";
+    assert_eq!(command.render(), expect);
+}
+
+#[test]
+fn test_command_result_ok() {
+    let result = CommandResult::ok("done");
+    assert!(result.success());
+    assert_eq!(result.code, Some(0));
+    assert_eq!(result.stdout, "done");
+    assert!(result.stderr.is_empty());
+    assert_eq!(result.exit_code(), 0);
+}
+
+#[test]
+fn test_command_result_fail() {
+    let mut result = CommandResult::fail(ErrorKind::Exit, "not found");
+    assert!(!result.success());
+    assert_eq!(result.error, Some(ErrorKind::Exit));
+    assert_eq!(result.stderr, "not found");
+    assert_eq!(result.exit_code(), 1);
+
+    result.code = Some(127);
+    assert_eq!(result.exit_code(), 127);
+}
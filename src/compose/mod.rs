@@ -0,0 +1,275 @@
+#[cfg(test)]
+mod test;
+
+use super::config::Ulimit;
+
+use serde::ser::{SerializeMap, Serializer};
+use serde::Serialize;
+use serde_derive::Serialize;
+use std::collections::BTreeMap;
+
+// Version 3 dropped resource limits, start_period of healthchecks and
+// conditions of depends_on, which are all supported by version 2.4
+const VERSION: &str = "2.4";
+
+// Typed model of a docker-compose file, serialized by a YAML library so that
+// all values are quoted and escaped when needed
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Compose {
+    pub version: String,
+    #[serde(serialize_with = "ordered_map")]
+    pub services: Vec<(String, ComposeService)>,
+    #[serde(
+        serialize_with = "ordered_map",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub networks: Vec<(String, ComposeNetwork)>,
+    #[serde(
+        serialize_with = "ordered_map",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub volumes: Vec<(String, ComposeVolume)>,
+}
+
+// Keys of the modeled service fields, which extra keys must not repeat
+pub const SERVICE_KEYS: &[&str] = &[
+    "image",
+    "container_name",
+    "hostname",
+    "user",
+    "build",
+    "ports",
+    "expose",
+    "working_dir",
+    "volumes",
+    "tmpfs",
+    "environment",
+    "env_file",
+    "depends_on",
+    "networks",
+    "network_mode",
+    "extra_hosts",
+    "dns",
+    "entrypoint",
+    "command",
+    "restart",
+    "healthcheck",
+    "tty",
+    "stdin_open",
+    "privileged",
+    "cap_add",
+    "ulimits",
+    "mem_limit",
+    "cpus",
+    "shm_size",
+    "logging",
+    "labels",
+];
+
+#[derive(Serialize, Debug, PartialEq, Default)]
+pub struct ComposeService {
+    pub image: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build: Option<ComposeBuild>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ports: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expose: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub working_dir: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub volumes: Option<Vec<ComposeServiceVolume>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tmpfs: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub environment: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub env_file: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub depends_on: Option<ComposeDependsOn>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub networks: Option<ComposeServiceNetworks>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub network_mode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extra_hosts: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dns: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entrypoint: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub restart: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub healthcheck: Option<ComposeHealthcheck>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tty: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stdin_open: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub privileged: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cap_add: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ulimits: Option<BTreeMap<String, Ulimit>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mem_limit: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpus: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shm_size: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logging: Option<ComposeLogging>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub labels: Option<Vec<String>>,
+    // Keys that are not modeled yet, written after all the others
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, toml::Value>,
+}
+
+// Short form lists names of services to start first, long form also tells
+// which condition of each service to wait for
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum ComposeDependsOn {
+    Short(Vec<String>),
+    #[serde(serialize_with = "ordered_map")]
+    Long(Vec<(String, ComposeCondition)>),
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct ComposeCondition {
+    pub condition: String,
+}
+
+// Short form is `source:target:mode`, long form gives each part by its name
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum ComposeServiceVolume {
+    Short(String),
+    Long(ComposeVolumeMount),
+}
+
+#[derive(Serialize, Debug, PartialEq, Default)]
+pub struct ComposeVolumeMount {
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    pub target: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub read_only: Option<bool>,
+}
+
+// Short form lists names of networks to join, long form also gives aliases
+// of the service in each network
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum ComposeServiceNetworks {
+    Short(Vec<String>),
+    #[serde(serialize_with = "ordered_map")]
+    Long(Vec<(String, ComposeServiceNetwork)>),
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct ComposeServiceNetwork {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+}
+
+#[derive(Serialize, Debug, PartialEq, Default)]
+pub struct ComposeNetwork {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub driver: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub internal: Option<bool>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct ComposeHealthcheck {
+    pub test: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interval: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retries: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_period: Option<String>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct ComposeBuild {
+    pub context: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dockerfile: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub args: Option<BTreeMap<String, String>>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct ComposeLogging {
+    pub driver: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub options: Option<BTreeMap<String, String>>,
+}
+
+#[derive(Serialize, Debug, PartialEq, Default)]
+pub struct ComposeVolume {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub driver: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub driver_opts: Option<BTreeMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub labels: Option<Vec<String>>,
+}
+
+impl Compose {
+    pub fn new() -> Self {
+        return Self {
+            version: String::from(VERSION),
+            services: Vec::new(),
+            networks: Vec::new(),
+            volumes: Vec::new(),
+        };
+    }
+
+    // Serialize to YAML text, ending with a new line
+    pub fn to_yaml(&self) -> Result<String, serde_yaml::Error> {
+        let mut text = serde_yaml::to_string(self)?;
+        if !text.ends_with('\n') {
+            text.push('\n');
+        }
+        return Ok(text);
+    }
+}
+
+// Serialize a list of entries as a map, keeping their order instead of
+// sorting them by key
+fn ordered_map<S, T>(
+    entries: &[(String, T)],
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: Serialize,
+{
+    let mut map = serializer.serialize_map(Some(entries.len()))?;
+    for (key, value) in entries {
+        map.serialize_entry(key, value)?;
+    }
+    return map.end();
+}
@@ -0,0 +1,106 @@
+use super::*;
+
+#[test]
+fn test_compose_to_yaml() {
+    let mut compose = Compose::new();
+
+    let service = ComposeService {
+        image: String::from("postgres:latest"),
+        environment: Some(vec![
+            String::from("PASSWORD=it's a \"secret\" # not a comment"),
+            String::from("DEBUG=true"),
+        ]),
+        command: Some(String::from("psql\n-U admin")),
+        ..Default::default()
+    };
+    compose.services.push((String::from("zebra"), service));
+
+    let service = ComposeService {
+        image: String::from("alpine"),
+        build: Some(ComposeBuild {
+            context: String::from("."),
+            dockerfile: None,
+            target: None,
+            args: None,
+        }),
+        ..Default::default()
+    };
+    compose.services.push((String::from("alpine"), service));
+
+    let expect = "---\n\
+                  version: \"2.4\"\n\
+                  services:\n  \
+                    zebra:\n    \
+                      image: \"postgres:latest\"\n    \
+                      environment:\n      \
+                        - \"PASSWORD=it's a \\\"secret\\\" # not a comment\"\n      \
+                        - DEBUG=true\n    \
+                      command: \"psql\\n-U admin\"\n  \
+                    alpine:\n    \
+                      image: alpine\n    \
+                      build:\n      \
+                        context: \".\"\n";
+    assert_eq!(compose.to_yaml().unwrap(), expect);
+}
+
+#[test]
+fn test_compose_resources_and_extra() {
+    let mut compose = Compose::new();
+
+    let mut ulimits = BTreeMap::new();
+    ulimits.insert(String::from("nproc"), Ulimit::Single(65535));
+    ulimits.insert(
+        String::from("nofile"),
+        Ulimit::Range {
+            soft: 20000,
+            hard: 40000,
+        },
+    );
+
+    let mut options = BTreeMap::new();
+    options.insert(String::from("max-size"), String::from("10m"));
+
+    let mut extra = BTreeMap::new();
+    extra.insert(
+        String::from("stop_grace_period"),
+        toml::Value::String(String::from("30s")),
+    );
+
+    let service = ComposeService {
+        image: String::from("alpine"),
+        tty: Some(true),
+        cap_add: Some(vec![String::from("SYS_PTRACE")]),
+        ulimits: Some(ulimits),
+        mem_limit: Some(String::from("512m")),
+        cpus: Some(1.5),
+        logging: Some(ComposeLogging {
+            driver: String::from("json-file"),
+            options: Some(options),
+        }),
+        extra: extra,
+        ..Default::default()
+    };
+    compose.services.push((String::from("alpine"), service));
+
+    let expect = "---\n\
+                  version: \"2.4\"\n\
+                  services:\n  \
+                    alpine:\n    \
+                      image: alpine\n    \
+                      tty: true\n    \
+                      cap_add:\n      \
+                        - SYS_PTRACE\n    \
+                      ulimits:\n      \
+                        nofile:\n        \
+                          soft: 20000\n        \
+                          hard: 40000\n      \
+                        nproc: 65535\n    \
+                      mem_limit: 512m\n    \
+                      cpus: 1.5\n    \
+                      logging:\n      \
+                        driver: json-file\n      \
+                        options:\n        \
+                          max-size: 10m\n    \
+                      stop_grace_period: 30s\n";
+    assert_eq!(compose.to_yaml().unwrap(), expect);
+}
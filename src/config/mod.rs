@@ -1,11 +1,15 @@
 #[cfg(test)]
 mod test;
 
+use super::util;
+
+use serde::{Serialize, Serializer};
 use serde_derive::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::fs;
 use std::io;
 use std::iter::FromIterator;
+use std::path::Path;
 
 const SERVICE_DIR_PATTERN: &str = "{SERVICE_DIR}";
 const REPO_DIR_PATTERN: &str = "{REPO_DIR}";
@@ -23,6 +27,31 @@ pub struct Machine {
 pub struct DockerBuild {
     pub context: String,
     pub docker_file: Option<String>,
+    pub target: Option<String>,
+    pub args: Option<BTreeMap<String, String>>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Healthcheck {
+    pub test: String,
+    pub interval: Option<String>,
+    pub timeout: Option<String>,
+    pub retries: Option<u32>,
+    pub start_period: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Logging {
+    pub driver: String,
+    pub options: Option<BTreeMap<String, String>>,
+}
+
+// A single limit, or different soft and hard limits
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Ulimit {
+    Single(i64),
+    Range { soft: i64, hard: i64 },
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -30,14 +59,61 @@ pub struct Docker {
     pub image: String,
     pub container_name: Option<String>,
     pub ports: Option<Vec<String>>,
+    pub expose: Option<Vec<String>>,
     pub working_dir: Option<String>,
+    // Short syntax `source:target:mode`, or long syntax
+    // `type=volume,source=name,target=/path,readonly`
     pub volumes: Option<Vec<String>>,
+    pub tmpfs: Option<Vec<String>>,
     pub environment: Option<Vec<String>>,
     pub env_file: Option<Vec<String>>,
+    // Names of services to start first, `name:condition` to wait for a
+    // condition such as `service_healthy`
     pub depends_on: Option<Vec<String>>,
+    // Names of networks to join, `name:alias1,alias2` to be reachable by
+    // other names in the network
+    pub networks: Option<Vec<String>>,
+    pub network_mode: Option<String>,
+    pub hostname: Option<String>,
+    pub extra_hosts: Option<Vec<String>>,
+    pub dns: Option<Vec<String>>,
+    pub user: Option<String>,
+    pub entrypoint: Option<String>,
     pub command: Option<String>,
+    pub restart: Option<String>,
+    pub tty: Option<bool>,
+    pub stdin_open: Option<bool>,
+    pub privileged: Option<bool>,
+    pub cap_add: Option<Vec<String>>,
+    pub mem_limit: Option<String>,
+    pub cpus: Option<f64>,
+    pub shm_size: Option<String>,
     pub labels: Option<Vec<String>>,
     pub build: Option<DockerBuild>,
+    pub healthcheck: Option<Healthcheck>,
+    pub logging: Option<Logging>,
+    #[serde(default, serialize_with = "tables_last")]
+    pub ulimits: Option<BTreeMap<String, Ulimit>>,
+    // Compose keys that are not modeled yet, copied as they are
+    #[serde(default, serialize_with = "tables_last")]
+    pub extra: Option<BTreeMap<String, toml::Value>>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Network {
+    pub name: String,
+    pub driver: Option<String>,
+    pub external: Option<bool>,
+    pub internal: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Volume {
+    pub name: String,
+    pub driver: Option<String>,
+    pub external: Option<bool>,
+    pub labels: Option<Vec<String>>,
+    pub driver_opts: Option<BTreeMap<String, String>>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -46,11 +122,40 @@ pub struct Dependency {
     pub docker: Docker,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub struct Repository {
     pub name: String,
     pub remote: String,
     pub local: String,
+    pub remote_name: Option<String>,
+    pub default_branch: Option<String>,
+    pub pull: Option<String>,
+    pub branch: Option<String>,
+    pub depth: Option<u32>,
+    pub protected_branches: Option<Vec<String>>,
+}
+
+impl Repository {
+    // Remote to pull from and push to, origin if not configured
+    pub fn remote_name(&self) -> &str {
+        return self.remote_name.as_ref().map_or("origin", String::as_ref);
+    }
+
+    // Main branch of the repository, master if not configured
+    pub fn default_branch(&self) -> &str {
+        return self.default_branch.as_ref().map_or("master", String::as_ref);
+    }
+
+    // Branches that need a confirmation before pushing to them, only the
+    // default branch if not configured
+    pub fn protected_branches(&self) -> Vec<&str> {
+        match &self.protected_branches {
+            Some(branches) => {
+                return branches.iter().map(String::as_ref).collect();
+            }
+            None => return vec![self.default_branch()],
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -58,6 +163,7 @@ pub struct Action {
     pub name: String,
     pub build: String,
     pub test: String,
+    pub timeout: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -88,18 +194,68 @@ pub struct Shortcut {
     pub prefixes: Vec<String>,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CustomCommand {
+    pub name: String,
+    pub description: Option<String>,
+    pub args: Option<Vec<String>>,
+    pub service: Option<String>,
+    pub dir: Option<String>,
+    pub timeout: Option<u64>,
+    pub steps: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct KeyBinding {
+    pub key: String,
+    pub action: Option<String>,
+    pub command: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Prompt {
+    pub template: Option<String>,
+    pub dir_max_length: Option<usize>,
+    pub edit_mode: Option<String>,
+    pub completion_type: Option<String>,
+    pub history_size: Option<usize>,
+    pub history_ignore_dups: Option<bool>,
+    pub bindings: Option<Vec<KeyBinding>>,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Config {
     pub project: String,
     pub using: Option<Vec<String>>,
+    pub parallel: Option<usize>,
+    pub env: Option<BTreeMap<String, String>>,
+    pub prompt: Option<Prompt>,
     pub machine: Option<Machine>,
     pub dependencies: Option<Vec<Dependency>>,
+    pub networks: Option<Vec<Network>>,
+    pub volumes: Option<Vec<Volume>>,
     pub repositories: Option<Vec<Repository>>,
     pub actions: Option<Vec<Action>>,
     pub patterns: Option<Vec<Pattern>>,
     pub services: Option<Vec<Service>>,
     pub groups: Option<Vec<Group>>,
     pub shortcuts: Option<Vec<Shortcut>>,
+    pub commands: Option<Vec<CustomCommand>>,
+}
+
+// Serialize plain values of a map before its tables, as toml requires
+fn tables_last<S, V>(
+    map: &Option<BTreeMap<String, V>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    V: Serialize,
+{
+    match map {
+        Some(map) => return toml::ser::tables_last(map, serializer),
+        None => return serializer.serialize_none(),
+    }
 }
 
 impl Config {
@@ -111,14 +267,20 @@ impl Config {
         return Self {
             project: String::from(project),
             using: None,
+            parallel: None,
+            env: None,
+            prompt: None,
             machine: None,
             dependencies: None,
+            networks: None,
+            volumes: None,
             repositories: None,
             actions: None,
             patterns: None,
             services: None,
             groups: None,
             shortcuts: None,
+            commands: None,
         };
     }
 
@@ -183,6 +345,19 @@ impl Config {
         return None;
     }
 
+    // Return the repository whose local directory contains dir
+    pub fn search_directory_repository(&self, dir: &str) -> Option<&Repository> {
+        if let Some(repositories) = &self.repositories {
+            for repository in repositories {
+                let local = util::normalize_path(&repository.local);
+                if Path::new(dir).starts_with(&local) {
+                    return Some(repository);
+                }
+            }
+        }
+        return None;
+    }
+
     pub fn search_service_repository(&self, name: &str) -> Option<&Repository> {
         if let Some(service) = self.search_service(name) {
             return self.search_repository(&service.repo);
@@ -216,6 +391,31 @@ impl Config {
         return None;
     }
 
+    pub fn search_command(&self, name: &str) -> Option<&CustomCommand> {
+        if let Some(commands) = &self.commands {
+            for command in commands {
+                if command.name == name {
+                    return Some(command);
+                }
+            }
+        }
+        return None;
+    }
+
+    // Environment variables of the project, with patterns in their values
+    // expanded, applied to every command
+    pub fn environment(&self) -> BTreeMap<String, String> {
+        let mut result = BTreeMap::new();
+
+        if let Some(env) = &self.env {
+            for (key, value) in env {
+                result.insert(key.to_owned(), self.fill_patterns(value, None));
+            }
+        }
+
+        return result;
+    }
+
     pub fn use_groups(&mut self, group_names: &[&str]) {
         let using: Vec<String> =
             group_names.iter().map(|s| String::from(*s)).collect();
@@ -271,6 +471,41 @@ impl Config {
         return result;
     }
 
+    // Return sorted names of repositories that match the names in args,
+    // directly or through their services or groups, or the first name that
+    // matches nothing. Special case: return repositories used by the using
+    // groups if args is empty, which may be none
+    pub fn match_repositories(
+        &self,
+        args: &[&str],
+    ) -> Result<Vec<String>, String> {
+        let mut result = BTreeSet::new();
+
+        if args.is_empty() {
+            result.extend(self.using_repositories());
+        }
+
+        for name in args {
+            if let Some(repository) = self.search_repository(name) {
+                result.insert(repository.name.to_owned());
+            } else if let Some(service) = self.search_service(name) {
+                result.insert(service.repo.to_owned());
+            } else if let Some(group) = self.search_group(name) {
+                if let Some(svc_names) = &group.services {
+                    for svc_name in svc_names {
+                        if let Some(service) = self.search_service(svc_name) {
+                            result.insert(service.repo.to_owned());
+                        }
+                    }
+                }
+            } else {
+                return Err(String::from(*name));
+            }
+        }
+
+        return Ok(result.into_iter().collect());
+    }
+
     // Return name of all services and/or dependencies that match the names in args
     // or having their group or repository names that match the names in args
     // Special case: return all if args is empty
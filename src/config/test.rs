@@ -20,6 +20,7 @@ fn test_load_config_ok() {
     assert!(config.patterns.is_some());
     assert!(config.services.is_some());
     assert!(config.groups.is_some());
+    assert!(config.commands.is_some());
 }
 
 #[test]
@@ -46,6 +47,31 @@ fn test_parse_config_invalid() {
     assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidData);
 }
 
+#[test]
+fn test_config_environment() {
+    let toml_text = r#"
+    project = "forest"
+
+    [env]
+    AWS_PROFILE = "dev"
+    DATA_DIR = "{DATA_DIR}/cache"
+
+    [[patterns]]
+    format = "{DATA_DIR}"
+    expand = "/tmp/forest"
+    "#;
+
+    let config = Config::parse(toml_text).expect("cannot parse config");
+    let env = config.environment();
+
+    let keys: Vec<&String> = env.keys().collect();
+    assert_eq!(keys, vec!["AWS_PROFILE", "DATA_DIR"]);
+    assert_eq!(env["AWS_PROFILE"], "dev");
+    assert_eq!(env["DATA_DIR"], "/tmp/forest/cache");
+
+    assert!(Config::new("forest").environment().is_empty());
+}
+
 #[test]
 fn test_save_config() {
     let config = sample_config();
@@ -91,6 +117,33 @@ fn test_search_repository_not_found() {
     assert!(found.is_none());
 }
 
+#[test]
+fn test_repository_git_settings() {
+    let config = sample_config();
+
+    let repository = config.search_repository("flowers").unwrap();
+    assert_eq!(repository.remote_name(), "origin");
+    assert_eq!(repository.default_branch(), "master");
+    assert_eq!(repository.protected_branches(), vec!["master"]);
+
+    let repository = config.search_repository("turtle").unwrap();
+    assert_eq!(repository.remote_name(), "upstream");
+    assert_eq!(repository.default_branch(), "main");
+    assert_eq!(repository.protected_branches(), vec!["main", "release"]);
+}
+
+#[test]
+fn test_search_directory_repository() {
+    let config = sample_config();
+
+    let dir = util::normalize_path("~/projects/flowers/lotus");
+    let repository = config.search_directory_repository(&dir).unwrap();
+    assert_eq!(repository.name, "flowers");
+
+    let dir = util::normalize_path("~/projects/flowers-old");
+    assert!(config.search_directory_repository(&dir).is_none());
+}
+
 #[test]
 fn test_search_action_found() {
     let config = sample_config();
@@ -101,6 +154,7 @@ fn test_search_action_found() {
 
     let action = found.unwrap();
     assert_eq!(action.name, name);
+    assert_eq!(action.timeout, Some(900));
 }
 
 #[test]
@@ -212,6 +266,27 @@ fn test_search_group_not_found() {
     assert!(found.is_none());
 }
 
+#[test]
+fn test_search_command_found() {
+    let config = sample_config();
+    let name = "migrate";
+
+    let found = config.search_command(name);
+    assert!(found.is_some());
+
+    let command = found.unwrap();
+    assert_eq!(command.name, name);
+}
+
+#[test]
+fn test_search_command_not_found() {
+    let config = sample_config();
+    let name = "unknown";
+
+    let found = config.search_command(name);
+    assert!(found.is_none());
+}
+
 #[test]
 fn test_using_dependencies() {
     let mut config = sample_config();
@@ -253,6 +328,25 @@ fn test_using_repositories() {
     assert!(repositories.is_empty());
 }
 
+#[test]
+fn test_match_repositories() {
+    let mut config = sample_config();
+
+    let result = config.match_repositories(&[]);
+    assert_eq!(result, Ok(vec![String::from("flowers")]));
+
+    let result = config.match_repositories(&["turtle", "svc", "lotus"]);
+    let expect = vec![String::from("flowers"), String::from("turtle")];
+    assert_eq!(result, Ok(expect));
+
+    let result = config.match_repositories(&["flowers", "tree"]);
+    assert_eq!(result, Err(String::from("tree")));
+
+    config.use_groups(&["dep"]);
+    let result = config.match_repositories(&[]);
+    assert_eq!(result, Ok(Vec::new()));
+}
+
 #[test]
 fn test_match_services_dependencies() {
     let config = sample_config();
@@ -14,3 +14,7 @@ pub fn green(text: &str) -> String {
 pub fn yellow(text: &str) -> String {
     return format!("{}{}{}", color::Fg(color::Yellow), text, style::Reset);
 }
+
+pub fn grey(text: &str) -> String {
+    return format!("{}{}{}", color::Fg(color::LightBlack), text, style::Reset);
+}
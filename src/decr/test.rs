@@ -20,3 +20,10 @@ fn test_yellow() {
     let expect = format!("{}yellow{}", color::Fg(color::Yellow), style::Reset);
     assert_eq!(output, expect);
 }
+
+#[test]
+fn test_grey() {
+    let output = grey("grey");
+    let expect = format!("{}grey{}", color::Fg(color::LightBlack), style::Reset);
+    assert_eq!(output, expect);
+}
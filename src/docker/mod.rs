@@ -6,10 +6,21 @@ use std::env;
 use std::fs;
 use std::io;
 
-use super::cmd::Command;
+use super::cmd::{Command, CommandResult, ErrorKind};
+use super::compose::{
+    Compose, ComposeBuild, ComposeCondition, ComposeDependsOn,
+    ComposeHealthcheck, ComposeLogging, ComposeNetwork, ComposeService,
+    ComposeServiceNetwork, ComposeServiceNetworks, ComposeServiceVolume,
+    ComposeVolume, ComposeVolumeMount, SERVICE_KEYS,
+};
 use super::config::{Config, Docker, Machine, Service};
 use super::decr;
 
+const DEFAULT_CONDITION: &str = "service_started";
+// Keys that tell the long form of a volume from a short one whose path has `=`
+const MOUNT_KEYS: &[&str] =
+    &["type", "source", "src", "target", "dst", "destination"];
+
 pub fn create_machine(machine: &Machine) -> Command {
     let raw = format!(
         "docker-machine create \
@@ -35,7 +46,7 @@ pub fn update_certificates(machine: &Machine) -> Command {
 pub fn load_environments(machine: &Machine) -> Command {
     let raw = format!("docker-machine env {}", machine.name);
 
-    let exec = |stdout: &str| -> (bool, String) {
+    let exec = |stdout: &str| -> CommandResult {
         let mut result: Vec<String> = Vec::new();
 
         for line in stdout.lines() {
@@ -56,7 +67,7 @@ pub fn load_environments(machine: &Machine) -> Command {
             }
         }
 
-        return (true, result.join("\n"));
+        return CommandResult::ok(&result.join("\n"));
     };
 
     return Command::new(&raw, "", false, true, true, Some(Box::new(exec)), true);
@@ -96,16 +107,28 @@ pub fn status_services(project: &str, compose_file: &str) -> Command {
                {{.Config.Image}}||\
                {{.State.StartedAt}}";
     let ps = format!("docker-compose -p {} -f {} ps -q", project, compose_file);
-    let raw = format!("docker inspect --format='{}' $({} | xargs)", fmt, ps);
+    // No pipe after `ps` so that its failure is the status of the assignment
+    let raw = format!(
+        "ids=$({}) && \
+         if [ -n \"$ids\" ]; then docker inspect --format='{}' $ids; fi",
+        ps, fmt
+    );
+
+    let exec = |stdout: &str| -> CommandResult {
+        if stdout.trim().is_empty() {
+            let output = "--> no running services";
+            println!("{}", output);
+            return CommandResult::ok(output);
+        }
 
-    let exec = |stdout: &str| -> (bool, String) {
         let mut lines: Vec<String> = Vec::new();
 
         let (mut ws, mut wt, mut wi) = (8, 8, 8);
         for line in stdout.lines() {
             let tokens: Vec<_> = line.split("||").collect();
             if tokens.len() != 4 {
-                return (false, format!("unexpected output line: {}", line));
+                let message = format!("unexpected output line: {}", line);
+                return CommandResult::fail(ErrorKind::Output, &message);
             }
 
             ws = cmp::max(ws, tokens[0].len());
@@ -150,10 +173,10 @@ pub fn status_services(project: &str, compose_file: &str) -> Command {
         let output = lines.join("\n");
         println!("{}", output);
 
-        return (true, output);
+        return CommandResult::ok(&output);
     };
 
-    return Command::new(&raw, "", false, true, true, Some(Box::new(exec)), true);
+    return Command::new(&raw, "", false, false, true, Some(Box::new(exec)), true);
 }
 
 pub fn compose_exec(service: &str, cmd: &str, project: &str, compose_file: &str) -> Command {
@@ -174,22 +197,24 @@ pub fn list_containers() -> Command {
 }
 
 pub fn generate_compose_file(file_path: &str, config: &Config) -> io::Result<()> {
-    let contents = generate_compose_text(config);
+    let contents = generate_compose_text(config)?;
     return fs::write(file_path, contents);
 }
 
-pub fn generate_compose_text(config: &Config) -> String {
-    let lines = generate_compose_lines(config);
-    return lines.join("\n");
+pub fn generate_compose_text(config: &Config) -> io::Result<String> {
+    let compose = compose_project(config)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    return compose
+        .to_yaml()
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err));
 }
 
-pub fn generate_compose_lines(config: &Config) -> Vec<String> {
-    let mut lines: Vec<String> = Vec::new();
+// Build the compose model of all dependencies and services in use, or return
+// an error message if one of them cannot be written
+pub fn compose_project(config: &Config) -> Result<Compose, String> {
+    let mut compose = Compose::new();
     let mut volumes: Vec<String> = Vec::new();
 
-    lines.push(String::from("version: '3'"));
-    lines.push(String::from("services:"));
-
     let using_dependencies = config.using_dependencies();
     let using_services = config.using_services();
 
@@ -198,9 +223,10 @@ pub fn generate_compose_lines(config: &Config) -> Vec<String> {
             if !using_dependencies.contains(&dependency.name) {
                 continue;
             }
-            let (more_lines, more_volumes) =
-                compose_service(&dependency.name, &dependency.docker, config, None);
-            lines.extend(more_lines);
+            check_extra(&dependency.docker, &dependency.name)?;
+            let (service, more_volumes) =
+                compose_service(&dependency.docker, config, None);
+            compose.services.push((dependency.name.to_owned(), service));
             volumes.extend(more_volumes);
         }
     }
@@ -210,130 +236,323 @@ pub fn generate_compose_lines(config: &Config) -> Vec<String> {
             if !using_services.contains(&service.name) {
                 continue;
             }
-            let (more_lines, more_volumes) =
-                compose_service(&service.name, &service.docker, config, Some(service));
-            lines.extend(more_lines);
+            check_extra(&service.docker, &service.name)?;
+            let (compose_service, more_volumes) =
+                compose_service(&service.docker, config, Some(service));
+            compose.services.push((service.name.to_owned(), compose_service));
             volumes.extend(more_volumes);
         }
     }
 
-    if !volumes.is_empty() {
-        lines.push(format!("volumes:"));
-        for v in volumes {
-            lines.push(format!("  {}:", v));
-            lines.push(format!("    external: {}", false));
+    // Only networks joined by services in use are written, unused external
+    // networks may not exist
+    let joined: Vec<String> = compose
+        .services
+        .iter()
+        .flat_map(|(_, service)| joined_networks(service))
+        .collect();
+
+    if let Some(networks) = &config.networks {
+        for network in networks {
+            if !joined.contains(&network.name) {
+                continue;
+            }
+            let compose_network = ComposeNetwork {
+                driver: network.driver.clone(),
+                external: network.external,
+                internal: network.internal,
+            };
+            compose.networks.push((network.name.to_owned(), compose_network));
         }
     }
 
-    lines.push(format!(""));
-
-    return lines;
-}
-
-fn compose_service(
-    name: &str,
-    docker: &Docker,
-    config: &Config,
-    service: Option<&Service>,
-) -> (Vec<String>, Vec<String>) {
-    let mut lines: Vec<String> = Vec::new();
-    let mut named_volumes: Vec<String> = Vec::new();
-
-    lines.push(format!("  {}:", name));
-    lines.push(format!("    image: {}", docker.image));
-
-    if let Some(container_name) = &docker.container_name {
-        lines.push(format!("    container_name: {}", container_name));
-    }
-
-    if let Some(build) = &docker.build {
-        lines.push(format!("    build:"));
-
-        let context = config.fill_patterns(&build.context, service);
-        lines.push(format!("      context: {}", context));
-
-        if let Some(docker_file) = &build.docker_file {
-            let docker_file = config.fill_patterns(docker_file, service);
-            lines.push(format!("      dockerfile: {}", docker_file));
+    // Only volumes used by services in use are written, declared settings
+    // come first
+    if let Some(declared) = &config.volumes {
+        for volume in declared {
+            if !volumes.contains(&volume.name) {
+                continue;
+            }
+            let compose_volume = ComposeVolume {
+                driver: volume.driver.clone(),
+                driver_opts: volume.driver_opts.clone(),
+                external: volume.external,
+                labels: volume.labels.clone(),
+            };
+            compose.volumes.push((volume.name.to_owned(), compose_volume));
         }
     }
 
-    if let Some(ports) = &docker.ports {
-        lines.push(format!("    ports:"));
-        for p in ports {
-            lines.push(format!("      - {}", p));
+    // Volumes that are not declared still need an entry with default settings
+    for v in volumes {
+        if compose.volumes.iter().all(|(name, _)| *name != v) {
+            compose.volumes.push((v, ComposeVolume::default()));
         }
     }
 
-    if let Some(working_dir) = &docker.working_dir {
-        let dir = config.fill_patterns(working_dir, service);
-        lines.push(format!("    working_dir: {}", dir));
-    }
-
-    if let Some(volumes) = &docker.volumes {
-        lines.push(format!("    volumes:"));
+    return Ok(compose);
+}
 
-        for v in volumes {
-            let volume = config.fill_patterns(v, service);
-            lines.push(format!("      - {}", volume));
+// Warn about named volumes used by dependencies or services but not declared
+// in the volumes table, and declared volumes that nobody uses
+pub fn volume_warnings(config: &Config) -> Vec<String> {
+    let mut used: Vec<(String, &str)> = Vec::new();
 
-            if let Some(named_volume) = extract_named_volume(&volume) {
-                named_volumes.push(named_volume);
+    if let Some(dependencies) = &config.dependencies {
+        for dependency in dependencies {
+            for v in named_volumes(&dependency.docker, config, None) {
+                used.push((v, &dependency.name));
             }
         }
     }
 
-    if let Some(environment) = &docker.environment {
-        lines.push(format!("    environment:"));
-        for e in environment {
-            lines.push(format!("      - {}", e));
+    if let Some(services) = &config.services {
+        for service in services {
+            for v in named_volumes(&service.docker, config, Some(service)) {
+                used.push((v, &service.name));
+            }
         }
     }
 
-    if let Some(env_file) = &docker.env_file {
-        lines.push(format!("    env_file:"));
-        for f in env_file {
-            let file = config.fill_patterns(f, service);
-            lines.push(format!("      - {}", file));
+    let declared: Vec<&str> = config
+        .volumes
+        .iter()
+        .flatten()
+        .map(|v| v.name.as_str())
+        .collect();
+
+    let mut warnings: Vec<String> = used
+        .iter()
+        .filter(|(v, _)| !declared.contains(&v.as_str()))
+        .map(|(v, name)| {
+            format!("--> undeclared volume [ {} ] of [ {} ]", v, name)
+        })
+        .collect();
+
+    for v in declared {
+        if used.iter().all(|(name, _)| name != v) {
+            warnings.push(format!("--> unused volume [ {} ]", v));
         }
     }
 
-    if let Some(depends_on) = &docker.depends_on {
-        lines.push(format!("    depends_on:"));
-        for d in depends_on {
-            lines.push(format!("      - {}", d));
+    return warnings;
+}
+
+fn named_volumes(
+    docker: &Docker,
+    config: &Config,
+    service: Option<&Service>,
+) -> Vec<String> {
+    return docker
+        .volumes
+        .iter()
+        .flatten()
+        .map(|v| config.fill_patterns(v, service))
+        .filter_map(|v| extract_named_volume(&v))
+        .collect();
+}
+
+fn compose_service(
+    docker: &Docker,
+    config: &Config,
+    service: Option<&Service>,
+) -> (ComposeService, Vec<String>) {
+    let fill = |text: &String| config.fill_patterns(text, service);
+    let fill_all = |texts: &Vec<String>| texts.iter().map(fill).collect();
+
+    let build = docker.build.as_ref().map(|build| ComposeBuild {
+        context: fill(&build.context),
+        dockerfile: build.docker_file.as_ref().map(fill),
+        target: build.target.clone(),
+        args: build.args.clone(),
+    });
+
+    let volumes: Option<Vec<ComposeServiceVolume>> = docker
+        .volumes
+        .as_ref()
+        .map(|v| v.iter().map(|v| compose_volume(&fill(v))).collect());
+
+    let compose_service = ComposeService {
+        image: docker.image.to_owned(),
+        container_name: docker.container_name.clone(),
+        hostname: docker.hostname.clone(),
+        user: docker.user.clone(),
+        build: build,
+        ports: docker.ports.clone(),
+        expose: docker.expose.clone(),
+        working_dir: docker.working_dir.as_ref().map(fill),
+        volumes: volumes,
+        tmpfs: docker.tmpfs.clone(),
+        environment: docker.environment.clone(),
+        env_file: docker.env_file.as_ref().map(fill_all),
+        depends_on: docker.depends_on.as_ref().map(|d| compose_depends_on(d)),
+        networks: docker.networks.as_ref().map(|n| compose_networks(n)),
+        network_mode: docker.network_mode.clone(),
+        extra_hosts: docker.extra_hosts.clone(),
+        dns: docker.dns.clone(),
+        entrypoint: docker.entrypoint.as_ref().map(fill),
+        command: docker.command.as_ref().map(fill),
+        restart: docker.restart.clone(),
+        healthcheck: docker.healthcheck.as_ref().map(|h| ComposeHealthcheck {
+            test: vec![String::from("CMD-SHELL"), fill(&h.test)],
+            interval: h.interval.clone(),
+            timeout: h.timeout.clone(),
+            retries: h.retries,
+            start_period: h.start_period.clone(),
+        }),
+        tty: docker.tty,
+        stdin_open: docker.stdin_open,
+        privileged: docker.privileged,
+        cap_add: docker.cap_add.clone(),
+        ulimits: docker.ulimits.clone(),
+        mem_limit: docker.mem_limit.clone(),
+        cpus: docker.cpus,
+        shm_size: docker.shm_size.clone(),
+        logging: docker.logging.as_ref().map(|l| ComposeLogging {
+            driver: l.driver.to_owned(),
+            options: l.options.clone(),
+        }),
+        labels: docker.labels.clone(),
+        extra: docker.extra.clone().unwrap_or_default(),
+    };
+
+    let named_volumes = named_volumes(docker, config, service);
+    return (compose_service, named_volumes);
+}
+
+// Extra keys are written next to the modeled fields, so repeating one of them
+// would write the same key twice
+fn check_extra(docker: &Docker, name: &str) -> Result<(), String> {
+    for key in docker.extra.iter().flat_map(|extra| extra.keys()) {
+        if SERVICE_KEYS.contains(&key.as_str()) {
+            return Err(format!(
+                "extra key [ {} ] of [ {} ] is already a docker field",
+                key, name
+            ));
         }
     }
+    return Ok(());
+}
+
+// Use the short form of depends_on unless a condition to wait for is given as
+// `name:condition`, other services then wait until they are started
+fn compose_depends_on(depends_on: &[String]) -> ComposeDependsOn {
+    let conditions: Vec<(String, ComposeCondition)> = depends_on
+        .iter()
+        .map(|d| {
+            let mut tokens = d.splitn(2, ':');
+            let name = tokens.next().unwrap_or_default();
+            let condition = tokens.next().unwrap_or(DEFAULT_CONDITION);
+            let condition = ComposeCondition {
+                condition: String::from(condition),
+            };
+            (String::from(name), condition)
+        })
+        .collect();
 
-    if let Some(command) = &docker.command {
-        let cmd = config.fill_patterns(command, service);
-        lines.push(format!("    command: {}", cmd));
+    if conditions.iter().all(|(_, c)| c.condition == DEFAULT_CONDITION) {
+        let names = conditions.into_iter().map(|(name, _)| name).collect();
+        return ComposeDependsOn::Short(names);
     }
+    return ComposeDependsOn::Long(conditions);
+}
 
-    if let Some(labels) = &docker.labels {
-        lines.push(format!("    labels:"));
-        for l in labels {
-            lines.push(format!("      - {}", l));
-        }
+// Use the short form of networks unless aliases are given as
+// `name:alias1,alias2`
+fn compose_networks(networks: &[String]) -> ComposeServiceNetworks {
+    if !networks.iter().any(|n| n.contains(':')) {
+        return ComposeServiceNetworks::Short(networks.to_vec());
     }
 
-    return (lines, named_volumes);
+    let aliases = networks
+        .iter()
+        .map(|n| {
+            let mut tokens = n.splitn(2, ':');
+            let name = tokens.next().unwrap_or_default();
+            let aliases = tokens
+                .next()
+                .map(|a| a.split(',').map(String::from).collect())
+                .unwrap_or_default();
+            (String::from(name), ComposeServiceNetwork { aliases: aliases })
+        })
+        .collect();
+    return ComposeServiceNetworks::Long(aliases);
 }
 
-fn extract_named_volume(name: &str) -> Option<String> {
-    let tokens: Vec<&str> = name.split(":").collect();
-    if tokens.len() != 2 {
-        return None;
+fn joined_networks(service: &ComposeService) -> Vec<String> {
+    return match &service.networks {
+        Some(ComposeServiceNetworks::Short(names)) => names.clone(),
+        Some(ComposeServiceNetworks::Long(networks)) => {
+            networks.iter().map(|(name, _)| name.clone()).collect()
+        },
+        None => Vec::new(),
+    };
+}
+
+// Use the long form of a volume when it is given as `key=value` pairs such as
+// `type=volume,source=name,target=/path,readonly`, like docker --mount
+fn compose_volume(volume: &str) -> ComposeServiceVolume {
+    let long = volume.split(',').any(|option| {
+        let mut tokens = option.splitn(2, '=');
+        let key = tokens.next().unwrap_or_default().trim();
+        tokens.next().is_some() && MOUNT_KEYS.contains(&key)
+    });
+    if !long {
+        return ComposeServiceVolume::Short(String::from(volume));
     }
 
-    let volume = tokens[0].to_owned();
-    let prefixes = ["/", ".", "~"];
+    let mut mount = ComposeVolumeMount {
+        kind: String::from("volume"),
+        ..Default::default()
+    };
 
-    for p in &prefixes {
-        if volume.starts_with(p) {
-            return None;
+    for option in volume.split(',') {
+        let mut tokens = option.splitn(2, '=');
+        let key = tokens.next().unwrap_or_default().trim();
+        let value = tokens.next().map(|v| v.trim());
+
+        match (key, value) {
+            ("type", Some(v)) => mount.kind = String::from(v),
+            ("source", Some(v)) | ("src", Some(v)) => {
+                mount.source = Some(String::from(v))
+            },
+            ("target", Some(v))
+            | ("dst", Some(v))
+            | ("destination", Some(v)) => mount.target = String::from(v),
+            ("readonly", None) | ("ro", None) => mount.read_only = Some(true),
+            ("readonly", Some(v)) | ("ro", Some(v)) => {
+                mount.read_only = Some(v == "true" || v == "1")
+            },
+            _ => {},
         }
     }
 
-    return Some(volume);
+    return ComposeServiceVolume::Long(mount);
+}
+
+// Name of the volume if it is a named volume, not a host path or an anonymous
+// volume
+fn extract_named_volume(volume: &str) -> Option<String> {
+    let source = match compose_volume(volume) {
+        ComposeServiceVolume::Short(short) => {
+            let tokens: Vec<&str> = short.split(':').collect();
+            if tokens.len() != 2 && tokens.len() != 3 {
+                return None;
+            }
+            String::from(tokens[0])
+        },
+        ComposeServiceVolume::Long(mount) => {
+            if mount.kind != "volume" {
+                return None;
+            }
+            mount.source?
+        },
+    };
+
+    let prefixes = ["/", ".", "~", "$"];
+    if source.is_empty() || prefixes.iter().any(|p| source.starts_with(p)) {
+        return None;
+    }
+
+    return Some(source);
 }
@@ -64,10 +64,10 @@ fn test_load_environments() {
                   DOCKER_MACHINE_NAME=turtle";
 
     let exec = command.then.unwrap();
-    let (success, result) = exec(stdout);
+    let result = exec(stdout);
 
-    assert!(success);
-    assert_eq!(result, expect);
+    assert!(result.success());
+    assert_eq!(result.stdout, expect);
 }
 
 #[test]
@@ -123,17 +123,17 @@ fn test_stop_services() {
 fn test_status_services() {
     let command = status_services("forest", "compose.yml");
 
-    let raw = "docker inspect --format='\
+    let raw = "ids=$(docker-compose -p forest -f compose.yml ps -q) \
+               && if [ -n \"$ids\" ]; then docker inspect --format='\
                {{index .Config.Labels \"com.docker.compose.service\"}}||\
                {{.State.Status}}||\
                {{.Config.Image}}||\
-               {{.State.StartedAt}}' \
-               $(docker-compose -p forest -f compose.yml ps -q | xargs)";
+               {{.State.StartedAt}}' $ids; fi";
 
     assert_eq!(command.raw, raw);
     assert!(command.dir.is_empty());
     assert!(!command.show);
-    assert!(command.silent);
+    assert!(!command.silent);
     assert!(command.pipe);
     assert!(command.then.is_some());
     assert!(command.back);
@@ -152,9 +152,17 @@ fn test_status_services() {
         decr::yellow("-"),
     );
 
-    let (success, output) = exec(stdout);
-    assert!(success);
-    assert_eq!(output, expect);
+    let result = exec(stdout);
+    assert!(result.success());
+    assert_eq!(result.stdout, expect);
+
+    let result = exec("");
+    assert!(result.success());
+    assert_eq!(result.stdout, "--> no running services");
+
+    let result = exec("camellia||running\n");
+    assert_eq!(result.error, Some(ErrorKind::Output));
+    assert_eq!(result.stderr, "unexpected output line: camellia||running");
 }
 
 #[test]
@@ -187,7 +195,7 @@ fn test_list_containers() {
 #[test]
 fn test_generate_compose_text() {
     let config = Config::load("etc/sample.config.toml").unwrap();
-    let result = generate_compose_text(&config);
+    let result = generate_compose_text(&config).unwrap();
     let expect = fs::read_to_string("etc/sample.compose.yml").unwrap();
     assert_eq!(result, expect);
 }
@@ -211,4 +219,169 @@ fn test_extract_named_volume() {
 
     let named_volume = volume.unwrap();
     assert_eq!(named_volume, "mysql_data");
+
+    let volume = extract_named_volume("mysql_data:/var/lib/mysql:ro");
+    assert_eq!(volume, Some(String::from("mysql_data")));
+
+    let volume = extract_named_volume("type=volume,src=cache,dst=/cache");
+    assert_eq!(volume, Some(String::from("cache")));
+
+    let volume = extract_named_volume("type=bind,source=./cache,target=/c");
+    assert!(volume.is_none());
+}
+
+#[test]
+fn test_compose_volume() {
+    let short = "mysql_data:/var/lib/mysql:ro";
+    let expect = ComposeServiceVolume::Short(String::from(short));
+    assert_eq!(compose_volume(short), expect);
+
+    let short = "./conf/a=b:/etc/app:ro";
+    let expect = ComposeServiceVolume::Short(String::from(short));
+    assert_eq!(compose_volume(short), expect);
+
+    let volume = compose_volume("source=mysql_data,target=/data,readonly");
+    let expect = ComposeServiceVolume::Long(ComposeVolumeMount {
+        kind: String::from("volume"),
+        source: Some(String::from("mysql_data")),
+        target: String::from("/data"),
+        read_only: Some(true),
+    });
+    assert_eq!(volume, expect);
+}
+
+#[test]
+fn test_volume_warnings() {
+    let toml_text = r#"
+    project = "forest"
+
+    [[dependencies]]
+    name = "mysql"
+
+    [dependencies.docker]
+    image = "mysql"
+    volumes = ["mysql_data:/var/lib/mysql", "/tmp:/tmp", "logs:/logs:ro"]
+
+    [[volumes]]
+    name = "mysql_data"
+
+    [[volumes]]
+    name = "backups"
+
+    [[groups]]
+    name = "db"
+    dependencies = ["mysql"]
+    "#;
+
+    let mut config = Config::parse(toml_text).expect("cannot parse config");
+    config.use_groups(&["db"]);
+
+    let expect = vec![
+        String::from("--> undeclared volume [ logs ] of [ mysql ]"),
+        String::from("--> unused volume [ backups ]"),
+    ];
+    assert_eq!(volume_warnings(&config), expect);
+
+    let compose = compose_project(&config).unwrap();
+    let names: Vec<&str> =
+        compose.volumes.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, vec!["mysql_data", "logs"]);
+}
+
+#[test]
+fn test_compose_project_extra() {
+    let toml_text = r#"
+    project = "forest"
+    using = ["db"]
+
+    [[dependencies]]
+    name = "mysql"
+
+    [dependencies.docker]
+    image = "mysql"
+    restart = "always"
+
+    [dependencies.docker.extra]
+    stop_grace_period = "30s"
+
+    [[groups]]
+    name = "db"
+    dependencies = ["mysql"]
+    "#;
+
+    let mut config = Config::parse(toml_text).expect("cannot parse config");
+    assert!(compose_project(&config).is_ok());
+
+    if let Some(dependencies) = config.dependencies.as_mut() {
+        let extra = dependencies[0].docker.extra.as_mut().unwrap();
+        extra.insert(String::from("restart"), toml::Value::from("no"));
+    }
+    let message = "extra key [ restart ] of [ mysql ] is already a docker field";
+    assert_eq!(compose_project(&config), Err(String::from(message)));
+}
+
+#[test]
+fn test_compose_depends_on() {
+    let depends_on = vec![String::from("postgres"), String::from("redis")];
+    let expect = ComposeDependsOn::Short(depends_on.clone());
+    assert_eq!(compose_depends_on(&depends_on), expect);
+
+    let depends_on = vec![
+        String::from("postgres:service_started"),
+        String::from("redis"),
+    ];
+    let names = vec![String::from("postgres"), String::from("redis")];
+    let expect = ComposeDependsOn::Short(names);
+    assert_eq!(compose_depends_on(&depends_on), expect);
+
+    let depends_on = vec![
+        String::from("postgres:service_healthy"),
+        String::from("redis"),
+    ];
+    let condition = |c: &str| ComposeCondition {
+        condition: String::from(c),
+    };
+    let expect = ComposeDependsOn::Long(vec![
+        (String::from("postgres"), condition("service_healthy")),
+        (String::from("redis"), condition("service_started")),
+    ]);
+    assert_eq!(compose_depends_on(&depends_on), expect);
+}
+
+#[test]
+fn test_compose_project_networks() {
+    let mut config = Config::load("etc/sample.config.toml").unwrap();
+    let compose = compose_project(&config).unwrap();
+    let names: Vec<&str> =
+        compose.networks.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, vec!["backend", "shared"]);
+
+    config.use_groups(&["dep"]);
+    let compose = compose_project(&config).unwrap();
+    assert!(compose.networks.is_empty());
+}
+
+#[test]
+fn test_compose_networks() {
+    let networks = vec![String::from("backend"), String::from("default")];
+    let expect = ComposeServiceNetworks::Short(networks.clone());
+    assert_eq!(compose_networks(&networks), expect);
+
+    let networks = vec![
+        String::from("backend:api.internal,api"),
+        String::from("shared"),
+    ];
+    let expect = ComposeServiceNetworks::Long(vec![
+        (
+            String::from("backend"),
+            ComposeServiceNetwork {
+                aliases: vec![String::from("api.internal"), String::from("api")],
+            },
+        ),
+        (
+            String::from("shared"),
+            ComposeServiceNetwork { aliases: Vec::new() },
+        ),
+    ]);
+    assert_eq!(compose_networks(&networks), expect);
 }
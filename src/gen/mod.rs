@@ -3,13 +3,16 @@ mod test;
 
 use super::brew;
 use super::cmd::Command;
-use super::config::Config;
+use super::config::{Config, CustomCommand, Repository};
 use super::dns;
 use super::docker;
 use super::git;
-use super::instr::Instruction;
+use super::instr::{Instruction, Strategy};
+use super::registry::{self, Completion, Descriptor, Usage};
 use super::util;
 
+use std::collections::BTreeMap;
+
 const QUIT: &str = "quit";
 const EXIT: &str = "exit";
 const INSTALL: &str = "install";
@@ -18,6 +21,15 @@ const GOTO: &str = "goto";
 const CLONE: &str = "clone";
 const PULL: &str = "pull";
 const PUSH: &str = "push";
+const GIT_STATUS: &str = "gstatus";
+const CHECKOUT: &str = "checkout";
+const BRANCH: &str = "branch";
+const STASH_FLAG: &str = "--stash";
+const DEPTH_FLAG: &str = "--depth";
+const BRANCH_FLAG: &str = "--branch";
+const FETCH: &str = "fetch";
+const SYNC: &str = "sync";
+const PRUNE: &str = "prune";
 const MACHINE: &str = "dkmc";
 const COMPOSE: &str = "dkcp";
 const DOCKER: &str = "dk";
@@ -32,12 +44,751 @@ const SH: &str = "sh";
 const BUILD: &str = "build";
 const TEST: &str = "test";
 const DNS: &str = "dns";
+const HELP: &str = "help";
+const DRYRUN: &str = "dryrun";
+const ENV: &str = "env";
+
+const MACHINE_SUBCOMMANDS: &[&str] = &[
+    "create",
+    "start",
+    "upcerts",
+    "load",
+    "active",
+    "config",
+    "env",
+    "inspect",
+    "ip",
+    "kill",
+    "ls",
+    "mount",
+    "provision",
+    "regenerate-certs",
+    "restart",
+    "rm",
+    "scp",
+    "ssh",
+    "status",
+    "stop",
+    "upgrade",
+    "url",
+    "version",
+];
+
+const DNS_SUBCOMMANDS: &[&str] = &["install", "restart", "update", "resolve"];
+
+const ENV_SUBCOMMANDS: &[&str] = &["set", "unset"];
+
+// All built-in commands, in the order they are listed by help and README.md
+pub const COMMANDS: &[Descriptor] = &[
+    Descriptor {
+        name: QUIT,
+        aliases: &[EXIT],
+        subcommands: &[],
+        free_args: 0,
+        completions: &[],
+        summary: "Exit turtle shell.",
+        usages: &[Usage {
+            args: "",
+            summary: "Exit turtle shell.",
+        }],
+        handler: |gen, _, _| gen.terminate(),
+    },
+    Descriptor {
+        name: HELP,
+        aliases: &[],
+        subcommands: &[],
+        free_args: 0,
+        completions: &[Completion::Commands],
+        summary: "Show supported commands or usage of a command.",
+        usages: &[
+            Usage {
+                args: "",
+                summary: "Show all supported commands.",
+            },
+            Usage {
+                args: "{command}",
+                summary: "Show usage of a specific command.",
+            },
+        ],
+        handler: |gen, _, args| gen.help(args),
+    },
+    Descriptor {
+        name: DRYRUN,
+        aliases: &[],
+        subcommands: &[],
+        free_args: 0,
+        completions: &[],
+        summary: "Print generated commands instead of running them.",
+        usages: &[
+            Usage {
+                args: "",
+                summary: "Toggle dry-run mode, which prints generated commands \
+                          instead of running them.",
+            },
+            Usage {
+                args: "on",
+                summary: "Turn on dry-run mode.",
+            },
+            Usage {
+                args: "off",
+                summary: "Turn off dry-run mode.",
+            },
+        ],
+        handler: |gen, _, args| gen.toggle_dry_run(args),
+    },
+    Descriptor {
+        name: ENV,
+        aliases: &[],
+        subcommands: ENV_SUBCOMMANDS,
+        free_args: 0,
+        completions: &[],
+        summary: "Manage environment variables of executed commands.",
+        usages: &[
+            Usage {
+                args: "",
+                summary: "Show environment variables set by the project \
+                          config and the current session.",
+            },
+            Usage {
+                args: "set [KEY1=VALUE1] [KEY2=VALUE2] ...",
+                summary: "Set environment variables for the next commands.",
+            },
+            Usage {
+                args: "unset [KEY1] [KEY2] ...",
+                summary: "Remove environment variables for the next commands.",
+            },
+        ],
+        handler: |gen, _, args| gen.environment(args),
+    },
+    Descriptor {
+        name: INSTALL,
+        aliases: &[],
+        subcommands: &[],
+        free_args: 0,
+        completions: &[],
+        summary: "Install Homebrew or packages using Homebrew.",
+        usages: &[
+            Usage {
+                args: "brew",
+                summary: "Install Homebrew.",
+            },
+            Usage {
+                args: "[package1] [package2] ..",
+                summary: "Install a list of packages using Homebrew.",
+            },
+        ],
+        handler: |gen, _, args| gen.install(args),
+    },
+    Descriptor {
+        name: CD,
+        aliases: &[],
+        subcommands: &[],
+        free_args: 0,
+        completions: &[],
+        summary: "Change current working directory.",
+        usages: &[Usage {
+            args: "{path}",
+            summary: "Change current working directory to the provided path.",
+        }],
+        handler: |gen, _, args| gen.change_directory(args),
+    },
+    Descriptor {
+        name: GOTO,
+        aliases: &[],
+        subcommands: &[],
+        free_args: 0,
+        completions: &[Completion::Services, Completion::Repositories],
+        summary: "Go to directory of a repository or service.",
+        usages: &[
+            Usage {
+                args: "{repository}",
+                summary: "Change current working directory to the provided \
+                          repository's directory.",
+            },
+            Usage {
+                args: "{service}",
+                summary: "Change current working directory to the provided \
+                          service's directory.",
+            },
+        ],
+        handler: |gen, _, args| gen.goto(args),
+    },
+    Descriptor {
+        name: CLONE,
+        aliases: &[],
+        subcommands: &[],
+        free_args: 0,
+        completions: &[Completion::Repositories],
+        summary: "Clone repositories.",
+        usages: &[
+            Usage {
+                args: "",
+                summary: "Clone all repositories in use that are not cloned \
+                          yet.",
+            },
+            Usage {
+                args: "[repository1] [repository2] ...",
+                summary: "Clone a list of provided repositories, skipping the \
+                          ones already cloned.",
+            },
+            Usage {
+                args: "--depth {n} --branch {branch} [repository1] ...",
+                summary: "Clone only the latest n commits of a branch, \
+                          overriding depth and branch in the config file.",
+            },
+        ],
+        handler: |gen, _, args| gen.clone_repositories(args),
+    },
+    Descriptor {
+        name: PULL,
+        aliases: &[],
+        subcommands: &[],
+        free_args: 0,
+        completions: &[
+            Completion::Repositories,
+            Completion::Services,
+            Completion::Groups,
+        ],
+        summary: "Git pull latest codes of repositories.",
+        usages: &[
+            Usage {
+                args: "",
+                summary: "Git pull latest codes on current branch of the \
+                          repository that contains current working directory.",
+            },
+            Usage {
+                args: "[repository1] [repository2] ...",
+                summary: "Git pull latest codes on current branch of the \
+                          provided repositories.",
+            },
+            Usage {
+                args: "[service1] [service2] ...",
+                summary: "Git pull latest codes on current branch of the \
+                          provided services' repositories.",
+            },
+            Usage {
+                args: "[group1] [group2] ...",
+                summary: "Git pull latest codes on current branch of \
+                          repositories of services in the provided groups.",
+            },
+        ],
+        handler: |gen, _, args| gen.pull_repositories(args),
+    },
+    Descriptor {
+        name: PUSH,
+        aliases: &[],
+        subcommands: &[],
+        free_args: 0,
+        completions: &[
+            Completion::Repositories,
+            Completion::Services,
+            Completion::Groups,
+        ],
+        summary: "Git push latest codes of repositories.",
+        usages: &[
+            Usage {
+                args: "",
+                summary: "Git push latest codes on current branch of the \
+                          repository that contains current working directory.",
+            },
+            Usage {
+                args: "[repository1] [repository2] ...",
+                summary: "Git push latest codes on current branch of the \
+                          provided repositories.",
+            },
+            Usage {
+                args: "[service1] [service2] ...",
+                summary: "Git push latest codes on current branch of the \
+                          provided services' repositories.",
+            },
+            Usage {
+                args: "[group1] [group2] ...",
+                summary: "Git push latest codes on current branch of \
+                          repositories of services in the provided groups.",
+            },
+        ],
+        handler: |gen, _, args| gen.push_repositories(args),
+    },
+    Descriptor {
+        name: FETCH,
+        aliases: &[],
+        subcommands: &[],
+        free_args: 0,
+        completions: &[
+            Completion::Repositories,
+            Completion::Services,
+            Completion::Groups,
+        ],
+        summary: "Git fetch latest changes of repositories.",
+        usages: &[
+            Usage {
+                args: "",
+                summary: "Fetch latest changes of all repositories in use and \
+                          prune deleted remote branches.",
+            },
+            Usage {
+                args: "[repository1] [repository2] ...",
+                summary: "Fetch latest changes of the provided repositories.",
+            },
+            Usage {
+                args: "[service1] [service2] ...",
+                summary: "Fetch latest changes of the provided services' \
+                          repositories.",
+            },
+            Usage {
+                args: "[group1] [group2] ...",
+                summary: "Fetch latest changes of repositories of services \
+                          in the provided groups.",
+            },
+        ],
+        handler: |gen, _, args| gen.fetch_repositories(args),
+    },
+    Descriptor {
+        name: SYNC,
+        aliases: &[],
+        subcommands: &[],
+        free_args: 0,
+        completions: &[
+            Completion::Repositories,
+            Completion::Services,
+            Completion::Groups,
+        ],
+        summary: "Fast-forward default branch of repositories.",
+        usages: &[
+            Usage {
+                args: "",
+                summary: "Fast-forward default branch of all repositories in \
+                          use to their remote without checking it out.",
+            },
+            Usage {
+                args: "[repository1] [repository2] ...",
+                summary: "Fast-forward default branch of the provided \
+                          repositories.",
+            },
+            Usage {
+                args: "[service1] [service2] ...",
+                summary: "Fast-forward default branch of the provided \
+                          services' repositories.",
+            },
+            Usage {
+                args: "[group1] [group2] ...",
+                summary: "Fast-forward default branch of repositories of \
+                          services in the provided groups.",
+            },
+        ],
+        handler: |gen, _, args| gen.sync_repositories(args),
+    },
+    Descriptor {
+        name: PRUNE,
+        aliases: &[],
+        subcommands: &[],
+        free_args: 0,
+        completions: &[
+            Completion::Repositories,
+            Completion::Services,
+            Completion::Groups,
+        ],
+        summary: "Delete merged local branches of repositories.",
+        usages: &[
+            Usage {
+                args: "",
+                summary: "Delete local branches of all repositories in use \
+                          that are already merged into their remote default \
+                          branch.",
+            },
+            Usage {
+                args: "[repository1] [repository2] ...",
+                summary: "Delete merged local branches of the provided \
+                          repositories.",
+            },
+            Usage {
+                args: "[service1] [service2] ...",
+                summary: "Delete merged local branches of the provided \
+                          services' repositories.",
+            },
+            Usage {
+                args: "[group1] [group2] ...",
+                summary: "Delete merged local branches of repositories of \
+                          services in the provided groups.",
+            },
+        ],
+        handler: |gen, _, args| gen.prune_repositories(args),
+    },
+    Descriptor {
+        name: GIT_STATUS,
+        aliases: &[],
+        subcommands: &[],
+        free_args: 0,
+        completions: &[
+            Completion::Repositories,
+            Completion::Services,
+            Completion::Groups,
+        ],
+        summary: "Show git status of repositories.",
+        usages: &[
+            Usage {
+                args: "",
+                summary: "Show branch, changes, sync state with upstream and \
+                          last commit of all repositories in use.",
+            },
+            Usage {
+                args: "[repository1] [repository2] ...",
+                summary: "Show git status of the provided repositories.",
+            },
+            Usage {
+                args: "[service1] [service2] ...",
+                summary: "Show git status of the provided services' \
+                          repositories.",
+            },
+            Usage {
+                args: "[group1] [group2] ...",
+                summary: "Show git status of repositories of services in the \
+                          provided groups.",
+            },
+        ],
+        handler: |gen, _, args| gen.status_repositories(args),
+    },
+    Descriptor {
+        name: CHECKOUT,
+        aliases: &[],
+        subcommands: &[],
+        free_args: 1,
+        completions: &[
+            Completion::Repositories,
+            Completion::Services,
+            Completion::Groups,
+        ],
+        summary: "Switch repositories to an existing branch.",
+        usages: &[
+            Usage {
+                args: "{branch}",
+                summary: "Switch all repositories in use to the branch.",
+            },
+            Usage {
+                args: "{branch} [name1] [name2] ...",
+                summary: "Switch repositories of the provided repository, \
+                          service or group names to the branch.",
+            },
+            Usage {
+                args: "{branch} --stash [name1] [name2] ...",
+                summary: "Stash uncommitted changes before switching instead \
+                          of refusing to switch.",
+            },
+        ],
+        handler: |gen, name, args| gen.checkout_branch(name, args),
+    },
+    Descriptor {
+        name: BRANCH,
+        aliases: &[],
+        subcommands: &[],
+        free_args: 1,
+        completions: &[
+            Completion::Repositories,
+            Completion::Services,
+            Completion::Groups,
+        ],
+        summary: "Create a branch in repositories and switch to it.",
+        usages: &[
+            Usage {
+                args: "{branch}",
+                summary: "Create the branch if missing and switch to it in \
+                          all repositories in use.",
+            },
+            Usage {
+                args: "{branch} [name1] [name2] ...",
+                summary: "Create the branch if missing and switch to it in \
+                          repositories of the provided repository, service or \
+                          group names.",
+            },
+            Usage {
+                args: "{branch} --stash [name1] [name2] ...",
+                summary: "Stash uncommitted changes before switching instead \
+                          of refusing to switch.",
+            },
+        ],
+        handler: |gen, name, args| gen.checkout_branch(name, args),
+    },
+    Descriptor {
+        name: MACHINE,
+        aliases: &[],
+        subcommands: MACHINE_SUBCOMMANDS,
+        free_args: 0,
+        completions: &[],
+        summary: "Manage the docker machine.",
+        usages: &[
+            Usage {
+                args: "{command}",
+                summary: "Run any docker-machine commands.",
+            },
+            Usage {
+                args: "create",
+                summary: "Create a new docker machine as described in the \
+                          config file.",
+            },
+            Usage {
+                args: "start",
+                summary: "Start the created docker machine.",
+            },
+            Usage {
+                args: "upcerts",
+                summary: "Update or regenerate the created docker machine's \
+                          certificates.",
+            },
+            Usage {
+                args: "load",
+                summary: "Load the created docker machine's environments.",
+            },
+        ],
+        handler: |gen, _, args| gen.machine(args),
+    },
+    Descriptor {
+        name: COMPOSE,
+        aliases: &[],
+        subcommands: &[],
+        free_args: 0,
+        completions: &[],
+        summary: "Run docker-compose commands.",
+        usages: &[Usage {
+            args: "{command}",
+            summary: "Run any docker-compose commands.",
+        }],
+        handler: |gen, _, args| gen.docker_compose(args),
+    },
+    Descriptor {
+        name: DOCKER,
+        aliases: &[],
+        subcommands: &[],
+        free_args: 0,
+        completions: &[],
+        summary: "Run docker commands.",
+        usages: &[Usage {
+            args: "{command}",
+            summary: "Run any docker commands.",
+        }],
+        handler: |gen, _, args| gen.docker(args),
+    },
+    Descriptor {
+        name: USE,
+        aliases: &[],
+        subcommands: &[],
+        free_args: 0,
+        completions: &[Completion::Groups],
+        summary: "Use groups of services.",
+        usages: &[Usage {
+            args: "{group}",
+            summary: "Generate or rewrite the working docker-compose file \
+                      for a group of services.",
+        }],
+        handler: |gen, _, args| gen.use_groups(args),
+    },
+    Descriptor {
+        name: START,
+        aliases: &[],
+        subcommands: &[],
+        free_args: 0,
+        completions: &[],
+        summary: "Start all services.",
+        usages: &[Usage {
+            args: "",
+            summary: "Start all services using the generated docker-compose \
+                      file.",
+        }],
+        handler: |gen, _, _| gen.start_services(),
+    },
+    Descriptor {
+        name: STATUS,
+        aliases: &[],
+        subcommands: &[],
+        free_args: 0,
+        completions: &[],
+        summary: "Show status of all services.",
+        usages: &[Usage {
+            args: "",
+            summary: "Show current status of all services.",
+        }],
+        handler: |gen, _, _| gen.status_services(),
+    },
+    Descriptor {
+        name: STOP,
+        aliases: &[],
+        subcommands: &[],
+        free_args: 0,
+        completions: &[Completion::Services, Completion::Dependencies],
+        summary: "Stop services.",
+        usages: &[
+            Usage {
+                args: "",
+                summary: "Stop all services using the generated \
+                          docker-compose file.",
+            },
+            Usage {
+                args: "[service1] [service2] ...",
+                summary: "Stop the provided services.",
+            },
+        ],
+        handler: |gen, _, args| gen.stop_services(args),
+    },
+    Descriptor {
+        name: RESTART,
+        aliases: &[],
+        subcommands: &[],
+        free_args: 0,
+        completions: &[Completion::Services, Completion::Dependencies],
+        summary: "Restart services.",
+        usages: &[
+            Usage {
+                args: "",
+                summary: "Restart all services using the generated \
+                          docker-compose file.",
+            },
+            Usage {
+                args: "[service1] [service2] ...",
+                summary: "Restart the provided services.",
+            },
+        ],
+        handler: |gen, _, args| gen.restart_services(args),
+    },
+    Descriptor {
+        name: LOGS,
+        aliases: &[],
+        subcommands: &[],
+        free_args: 0,
+        completions: &[Completion::Services, Completion::Dependencies],
+        summary: "Show logs of a service.",
+        usages: &[Usage {
+            args: "{service}",
+            summary: "Show and follow logs of a specific service.",
+        }],
+        handler: |gen, _, args| gen.service_logs(args),
+    },
+    Descriptor {
+        name: BUILD,
+        aliases: &[],
+        subcommands: &[],
+        free_args: 0,
+        completions: &[
+            Completion::Services,
+            Completion::Repositories,
+            Completion::Groups,
+        ],
+        summary: "Build services.",
+        usages: &[
+            Usage {
+                args: "",
+                summary: "Build all services using the command specified in \
+                          the config file.",
+            },
+            Usage {
+                args: "[service1] [service2] ...",
+                summary: "Build the provided services.",
+            },
+            Usage {
+                args: "[repository1] [repository2] ...",
+                summary: "Build services in the provided repositories.",
+            },
+            Usage {
+                args: "[group1] [group2] ...",
+                summary: "Build services in the provided groups.",
+            },
+        ],
+        handler: |gen, name, args| gen.do_services(name, args),
+    },
+    Descriptor {
+        name: TEST,
+        aliases: &[],
+        subcommands: &[],
+        free_args: 0,
+        completions: &[
+            Completion::Services,
+            Completion::Repositories,
+            Completion::Groups,
+        ],
+        summary: "Test services.",
+        usages: &[
+            Usage {
+                args: "",
+                summary: "Test all services using the command specified in \
+                          the config file.",
+            },
+            Usage {
+                args: "[service1] [service2] ...",
+                summary: "Test the provided services.",
+            },
+            Usage {
+                args: "[repository1] [repository2] ...",
+                summary: "Test services in the provided repositories.",
+            },
+            Usage {
+                args: "[group1] [group2] ...",
+                summary: "Test services in the provided groups.",
+            },
+        ],
+        handler: |gen, name, args| gen.do_services(name, args),
+    },
+    Descriptor {
+        name: SH,
+        aliases: &[],
+        subcommands: &[],
+        free_args: 0,
+        completions: &[Completion::Services, Completion::Dependencies],
+        summary: "Access sh shell of a service.",
+        usages: &[Usage {
+            args: "{service}",
+            summary: "Access `/bin/sh` shell of a specific service.",
+        }],
+        handler: |gen, name, args| gen.open_service_shell(name, args),
+    },
+    Descriptor {
+        name: BASH,
+        aliases: &[],
+        subcommands: &[],
+        free_args: 0,
+        completions: &[Completion::Services, Completion::Dependencies],
+        summary: "Access bash shell of a service.",
+        usages: &[Usage {
+            args: "{service}",
+            summary: "Access `/bin/bash` shell of a specific service.",
+        }],
+        handler: |gen, name, args| gen.open_service_shell(name, args),
+    },
+    Descriptor {
+        name: DNS,
+        aliases: &[],
+        subcommands: DNS_SUBCOMMANDS,
+        free_args: 0,
+        completions: &[],
+        summary: "Manage dnsmasq for the docker machine's domain.",
+        usages: &[
+            Usage {
+                args: "install",
+                summary: "Install dnsmasq using Homebrew.",
+            },
+            Usage {
+                args: "restart",
+                summary: "Restart dnsmasq service.",
+            },
+            Usage {
+                args: "update",
+                summary: "Point the docker machine's domain to its ip address \
+                          in dnsmasq config.",
+            },
+            Usage {
+                args: "resolve",
+                summary: "Resolve the docker machine's domain using dnsmasq.",
+            },
+        ],
+        handler: |gen, _, args| gen.do_dns(args),
+    },
+];
 
 #[derive(Debug)]
 pub struct Generator {
     config: Config,
     config_file: String,
     compose_file: String,
+    dry_run: bool,
+    verbose: bool,
+    env: BTreeMap<String, Option<String>>,
 }
 
 impl Generator {
@@ -71,48 +822,93 @@ impl Generator {
             cfg
         };
 
+        let env = config
+            .environment()
+            .into_iter()
+            .map(|(key, value)| (key, Some(value)))
+            .collect();
+
         let generator = Self {
             config,
             config_file,
             compose_file,
+            dry_run: false,
+            verbose: false,
+            env,
         };
         return Ok(generator);
     }
 
+    pub fn config(&self) -> &Config {
+        return &self.config;
+    }
+
+    // Print generated instructions instead of executing them
+    pub fn set_dry_run(&mut self, dry_run: bool) {
+        self.dry_run = dry_run;
+    }
+
+    // Show every generated command before executing it
+    pub fn set_verbose(&mut self, verbose: bool) {
+        self.verbose = verbose;
+    }
+
     // Takes a raw instruction string, returns a list of instructions to execute
     pub fn generate_instruction(&mut self, raw: &str) -> Instruction {
-        let mut raw = util::normalize_spaces(raw);
+        let dry_run = self.dry_run;
+        let mut instruction = self.dispatch(raw);
+
+        let commands = instruction.commands.drain(..);
+        instruction.commands =
+            commands.map(|command| command.with_env(&self.env)).collect();
+
+        if self.verbose {
+            for command in &mut instruction.commands {
+                command.show = true;
+            }
+        }
+
+        // The instruction that switches dry-run mode on or off must still
+        // run, so its confirmation message is printed
+        instruction.dry_run = dry_run && self.dry_run;
+        return instruction;
+    }
+
+    fn dispatch(&mut self, raw: &str) -> Instruction {
+        // Shortcuts see the line as typed, so quoted arguments keep their
+        // spaces
+        let mut raw = String::from(raw.trim());
         if let Some(shortcuts) = &self.config.shortcuts {
-            raw = util::replace_shortcuts(&raw, shortcuts);
-        }
-
-        let mut tokens = raw.trim().split_whitespace();
-
-        if let Some(program) = tokens.next() {
-            let args: Vec<&str> = tokens.collect();
-
-            match program {
-                QUIT | EXIT => return self.terminate(),
-                INSTALL => return self.install(&args),
-                CD => return self.change_directory(&args),
-                GOTO => return self.goto(&args),
-                CLONE => return self.clone_repositories(&args),
-                PULL => return self.pull_repositories(&args),
-                PUSH => return self.push_repositories(&args),
-                MACHINE => return self.machine(&args),
-                COMPOSE => return self.docker_compose(&args),
-                DOCKER => return self.docker(&args),
-                LOGS => return self.service_logs(&args),
-                USE => return self.use_groups(&args),
-                START => return self.start_services(),
-                STOP => return self.stop_services(&args),
-                RESTART => return self.restart_services(&args),
-                STATUS => return self.status_services(),
-                BASH | SH => return self.open_service_shell(&program, &args),
-                BUILD | TEST => return self.do_services(&program, &args),
-                DNS => return self.do_dns(&args),
-                _ => return self.other(&raw),
+            match util::replace_shortcuts(&raw, shortcuts) {
+                Ok(text) => raw = text,
+                Err(message) => return Instruction::error(&message),
+            }
+        }
+
+        // Words keep their quotes, so handlers passing them to the shell
+        // don't change the meaning of arguments, others get their values
+        let words = util::split_shell_words(&raw);
+        let values: Vec<String> =
+            words.iter().map(|word| util::unquote(word)).collect();
+        let values: Vec<&str> = values.iter().map(String::as_ref).collect();
+
+        if let Some((program, args)) = values.split_first() {
+            if let Some(descriptor) = registry::search(COMMANDS, program) {
+                if let Some(message) = descriptor.check_subcommand(args) {
+                    return Instruction::error(&message);
+                }
+                let args = match descriptor.name {
+                    MACHINE | COMPOSE | DOCKER => &words[1..],
+                    _ => args,
+                };
+                return (descriptor.handler)(self, descriptor.name, args);
+            }
+
+            if let Some(custom) = self.config.search_command(program) {
+                return self.custom_command(custom, args);
             }
+
+            return self.other(&raw);
         }
 
         return Instruction::skip();
@@ -122,6 +918,115 @@ impl Generator {
         return Instruction::terminate();
     }
 
+    fn help(&self, args: &[&str]) -> Instruction {
+        if let Some(name) = args.first() {
+            if let Some(descriptor) = registry::search(COMMANDS, name) {
+                return Instruction::echo(&registry::usage(descriptor));
+            }
+
+            if let Some(custom) = self.config.search_command(name) {
+                return Instruction::echo(&registry::custom_usage(custom));
+            }
+
+            let message = format!("--> unknown command [ {} ]", name);
+            return Instruction::error(&message);
+        }
+
+        let customs = match &self.config.commands {
+            Some(commands) => &commands[..],
+            None => &[],
+        };
+        return Instruction::echo(&registry::help(COMMANDS, customs));
+    }
+
+    fn toggle_dry_run(&mut self, args: &[&str]) -> Instruction {
+        self.dry_run = match args.first() {
+            Some(&"on") => true,
+            Some(&"off") => false,
+            Some(value) => {
+                let descriptor = registry::search(COMMANDS, DRYRUN).unwrap();
+                let message = format!(
+                    "--> unknown value [ {} ] of [ {} ]\n{}",
+                    value,
+                    DRYRUN,
+                    registry::usage(descriptor)
+                );
+                return Instruction::error(&message);
+            }
+            None => !self.dry_run,
+        };
+
+        let state = if self.dry_run { "on" } else { "off" };
+        let message = format!("--> dry-run mode is {}", state);
+        return Instruction::echo(&message);
+    }
+
+    fn environment(&mut self, args: &[&str]) -> Instruction {
+        match args.split_first() {
+            Some((_, [])) => {
+                let descriptor = registry::search(COMMANDS, ENV).unwrap();
+                let message = format!(
+                    "--> missing variables of [ {} ]\n{}",
+                    ENV,
+                    registry::usage(descriptor)
+                );
+                return Instruction::error(&message);
+            }
+            Some((&"set", vars)) => return self.set_environment(vars),
+            Some((_, keys)) => return self.unset_environment(keys),
+            None => return self.show_environment(),
+        }
+    }
+
+    fn show_environment(&self) -> Instruction {
+        let mut lines: Vec<String> = Vec::new();
+        for (key, value) in &self.env {
+            if let Some(value) = value {
+                lines.push(format!("{}={}", key, value));
+            }
+        }
+
+        if lines.is_empty() {
+            return Instruction::echo("--> no environment variables");
+        }
+        return Instruction::echo(&lines.join("\n"));
+    }
+
+    fn set_environment(&mut self, vars: &[&str]) -> Instruction {
+        let mut pairs: Vec<(&str, &str)> = Vec::with_capacity(vars.len());
+        for var in vars {
+            match var.find('=') {
+                Some(i) if i > 0 => pairs.push((&var[..i], &var[i + 1..])),
+                _ => {
+                    let message = format!(
+                        "--> invalid variable [ {} ], expected KEY=VALUE",
+                        var
+                    );
+                    return Instruction::error(&message);
+                }
+            }
+        }
+
+        let mut keys: Vec<&str> = Vec::with_capacity(pairs.len());
+        for (key, value) in pairs {
+            let value = self.config.fill_patterns(value, None);
+            self.env.insert(String::from(key), Some(value));
+            keys.push(key);
+        }
+
+        let message = format!("--> set [ {} ]", keys.join(" "));
+        return Instruction::echo(&message);
+    }
+
+    fn unset_environment(&mut self, keys: &[&str]) -> Instruction {
+        for key in keys {
+            self.env.insert(String::from(*key), None);
+        }
+
+        let message = format!("--> unset [ {} ]", keys.join(" "));
+        return Instruction::echo(&message);
+    }
+
     fn install(&self, args: &[&str]) -> Instruction {
         let command = if args.contains(&"brew") {
             brew::install_brew()
@@ -154,70 +1059,293 @@ impl Generator {
 
             let message =
                 format!("--> unknown service or repository [ {} ]", name);
-            return Instruction::echo(&message);
+            return Instruction::error(&message);
         }
 
         return Instruction::skip();
     }
 
     fn clone_repositories(&self, args: &[&str]) -> Instruction {
-        if args.is_empty() {
-            let repos = self.config.using_repositories();
-            if repos.is_empty() {
-                return Instruction::echo("--> nothing to clone");
+        let mut depth: Option<u32> = None;
+        let mut branch: Option<&str> = None;
+        let mut names: Vec<&str> = Vec::with_capacity(args.len());
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            match *arg {
+                DEPTH_FLAG => match iter.next().map(|value| value.parse()) {
+                    Some(Ok(value)) if value > 0 => depth = Some(value),
+                    Some(_) => {
+                        let message = format!(
+                            "--> invalid value of [ {} ], expected a \
+                             positive number",
+                            DEPTH_FLAG
+                        );
+                        return Instruction::error(&message);
+                    }
+                    None => {
+                        let message =
+                            format!("--> missing value of [ {} ]", DEPTH_FLAG);
+                        return Instruction::error(&message);
+                    }
+                },
+                BRANCH_FLAG => match iter.next() {
+                    Some(value) => branch = Some(value),
+                    None => {
+                        let message =
+                            format!("--> missing value of [ {} ]", BRANCH_FLAG);
+                        return Instruction::error(&message);
+                    }
+                },
+                name => names.push(name),
             }
+        }
 
-            let repos: Vec<&str> = repos.iter().map(String::as_ref).collect();
-            return self.clone_repositories(&repos);
+        let using = self.config.using_repositories();
+        if names.is_empty() {
+            names = using.iter().map(String::as_ref).collect();
+            names.sort();
         }
 
-        let mut commands: Vec<Command> = Vec::with_capacity(args.len());
-        for name in args {
+        let mut commands: Vec<Command> = Vec::with_capacity(names.len());
+        let mut skipped: Vec<&str> = Vec::new();
+        let mut unknown = false;
+        for name in names {
             if let Some(repository) = self.config.search_repository(name) {
-                commands.push(git::clone_repository(repository));
+                let local = util::normalize_path(&repository.local);
+                if util::path_exist(&format!("{}/.git", local)) {
+                    skipped.push(name);
+                    continue;
+                }
+
+                let command = git::clone_repository(
+                    repository,
+                    depth.or(repository.depth),
+                    branch.or(repository.branch.as_ref().map(String::as_ref)),
+                );
+                commands.push(command.labeled(name));
             } else {
                 let message = format!("--> unknown repository [ {} ]", name);
                 commands.push(Command::echo(&message));
+                unknown = true;
             }
         }
 
-        return Instruction::basic(commands);
+        if !skipped.is_empty() {
+            let message = format!(
+                "--> skip already cloned repositories [ {} ]",
+                skipped.join(" ")
+            );
+            if commands.is_empty() {
+                return Instruction::echo(&message);
+            }
+            commands.insert(0, Command::echo(&message));
+        }
+
+        if commands.is_empty() {
+            return Instruction::echo("--> nothing to clone");
+        }
+
+        let mut instruction =
+            Instruction::with_strategy(commands, self.strategy());
+        instruction.error = unknown;
+        return instruction;
     }
 
     fn pull_repositories(&self, args: &[&str]) -> Instruction {
-        return self.git_do_repositories(args, git::pull_repository);
+        let strategy = self.strategy();
+        return self.git_do_repositories(args, git::pull_repository, strategy);
     }
 
     fn push_repositories(&self, args: &[&str]) -> Instruction {
-        return self.git_do_repositories(args, git::push_repository);
+        // Pushes may ask for a confirmation, so run them one by one
+        let push = |repo: &Repository| Ok(git::push_repository(repo));
+        let strategy = Strategy::ContinueOnError;
+        return self.git_do_repositories(args, push, strategy);
+    }
+
+    fn status_repositories(&self, args: &[&str]) -> Instruction {
+        let names = match self.config.match_repositories(args) {
+            Ok(names) => names,
+            Err(name) => {
+                let message = format!(
+                    "--> unknown repository, service or group [ {} ]",
+                    name
+                );
+                return Instruction::error(&message);
+            }
+        };
+
+        let repositories: Vec<_> = names
+            .iter()
+            .filter_map(|name| self.config.search_repository(name))
+            .collect();
+        if repositories.is_empty() && args.is_empty() {
+            return Instruction::echo("--> no repositories in use");
+        } else if repositories.is_empty() {
+            return Instruction::echo("--> no repositories");
+        }
+
+        let command = git::status_repositories(&repositories);
+        return Instruction::basic(vec![command]);
+    }
+
+    fn checkout_branch(&self, name: &str, args: &[&str]) -> Instruction {
+        let stash = args.contains(&STASH_FLAG);
+        let args: Vec<&str> =
+            args.iter().cloned().filter(|arg| *arg != STASH_FLAG).collect();
+
+        let (branch, targets) = match args.split_first() {
+            Some((branch, targets)) => (branch, targets),
+            None => {
+                let descriptor = registry::search(COMMANDS, name).unwrap();
+                let message = format!(
+                    "--> missing branch of [ {} ]\n{}",
+                    name,
+                    registry::usage(descriptor)
+                );
+                return Instruction::error(&message);
+            }
+        };
+
+        let names = match self.config.match_repositories(targets) {
+            Ok(names) => names,
+            Err(name) => {
+                let message = format!(
+                    "--> unknown repository, service or group [ {} ]",
+                    name
+                );
+                return Instruction::error(&message);
+            }
+        };
+
+        let create = name == BRANCH;
+        let mut commands: Vec<Command> = Vec::with_capacity(names.len());
+        for name in &names {
+            if let Some(repository) = self.config.search_repository(name) {
+                let command = git::checkout_branch(
+                    &repository.local,
+                    branch,
+                    create,
+                    stash,
+                );
+                commands.push(command.labeled(name));
+            }
+        }
+
+        if commands.is_empty() && targets.is_empty() {
+            return Instruction::echo("--> no repositories in use");
+        } else if commands.is_empty() {
+            return Instruction::echo("--> no repositories");
+        }
+        return Instruction::with_strategy(commands, self.strategy());
+    }
+
+    fn fetch_repositories(&self, args: &[&str]) -> Instruction {
+        let fetch = |repo: &Repository| Ok(git::fetch_repository(repo));
+        return self.git_do_all_repositories(args, fetch);
+    }
+
+    fn sync_repositories(&self, args: &[&str]) -> Instruction {
+        let sync = |repo: &Repository| Ok(git::sync_repository(repo));
+        return self.git_do_all_repositories(args, sync);
+    }
+
+    fn prune_repositories(&self, args: &[&str]) -> Instruction {
+        let prune = |repo: &Repository| Ok(git::prune_repository(repo));
+        return self.git_do_all_repositories(args, prune);
+    }
+
+    // Same as git_do_repositories, but all repositories in use are chosen
+    // instead of the current directory when args is empty
+    fn git_do_all_repositories(
+        &self,
+        args: &[&str],
+        doit: fn(&Repository) -> Result<Command, String>,
+    ) -> Instruction {
+        if !args.is_empty() {
+            return self.git_do_repositories(args, doit, self.strategy());
+        }
+
+        let names = self.config.match_repositories(args).unwrap_or_default();
+        if names.is_empty() {
+            return Instruction::echo("--> no repositories in use");
+        }
+
+        let names: Vec<&str> = names.iter().map(String::as_ref).collect();
+        return self.git_do_repositories(&names, doit, self.strategy());
     }
 
     fn git_do_repositories(
         &self,
         args: &[&str],
-        doit: fn(&str) -> Command,
+        doit: fn(&Repository) -> Result<Command, String>,
+        strategy: Strategy,
     ) -> Instruction {
         if args.is_empty() {
-            let command = doit("");
-            return Instruction::basic(vec![command]);
+            // Use settings of the configured repository of current directory
+            let dir = util::current_directory();
+            let current = Repository::default();
+            let repository = self
+                .config
+                .search_directory_repository(&dir)
+                .map_or(&current, |repository| repository);
+
+            match doit(repository) {
+                Ok(command) => {
+                    let command = Command { dir: String::new(), ..command };
+                    return Instruction::basic(vec![command]);
+                }
+                Err(message) => {
+                    return Instruction::error(&format!("--> {}", message));
+                }
+            }
         }
 
-        let mut commands: Vec<Command> = Vec::with_capacity(args.len());
+        let mut targets: Vec<(&str, Option<&Repository>)> = Vec::new();
         for name in args {
-            if let Some(repository) = self.config.search_repository(name) {
-                commands.push(doit(&repository.local));
-            } else if let Some(repository) =
-                self.config.search_service_repository(name)
-            {
-                commands.push(doit(&repository.local));
+            if self.config.search_group(name).is_some() {
+                // Groups are expanded to repositories of their services
+                let names = self.config.match_repositories(&[name]);
+                for repo_name in names.unwrap_or_default() {
+                    if let Some(repository) =
+                        self.config.search_repository(&repo_name)
+                    {
+                        targets.push((&repository.name, Some(repository)));
+                    }
+                }
             } else {
-                let message =
-                    format!("--> unknown repository or service [ {} ]", name);
-                commands.push(Command::echo(&message));
+                let repository = self
+                    .config
+                    .search_repository(name)
+                    .or_else(|| self.config.search_service_repository(name));
+                targets.push((name, repository));
             }
         }
 
-        return Instruction::basic(commands);
+        let mut commands: Vec<Command> = Vec::with_capacity(targets.len());
+        let mut error = false;
+        for (name, repository) in targets {
+            let command = match repository.map(doit) {
+                Some(Ok(command)) => command.labeled(name),
+                Some(Err(message)) => {
+                    error = true;
+                    Command::echo(&format!("--> {}", message)).labeled(name)
+                }
+                None => {
+                    error = true;
+                    Command::echo(&format!(
+                        "--> unknown repository, service or group [ {} ]",
+                        name
+                    ))
+                }
+            };
+            commands.push(command);
+        }
+
+        let mut instruction = Instruction::with_strategy(commands, strategy);
+        instruction.error = error;
+        return instruction;
     }
 
     fn machine(&self, args: &[&str]) -> Instruction {
@@ -247,7 +1375,7 @@ impl Generator {
                 }
             }
             None => {
-                return Instruction::echo(
+                return Instruction::error(
                     "--> docker machine config is not found",
                 )
             }
@@ -366,12 +1494,12 @@ impl Generator {
                     );
                     return Instruction::basic(vec![command]);
                 }
-                return Instruction::echo("--> service name is not provided");
+                return Instruction::error("--> service name is not provided");
             }
             _ => {
                 let message =
                     format!("--> unknown shell type [ {} ]", shell_type);
-                return Instruction::echo(&message);
+                return Instruction::error(&message);
             }
         }
     }
@@ -404,14 +1532,15 @@ impl Generator {
 
                         let command = Command::new(
                             raw, &dir, true, false, false, None, true,
-                        );
-                        commands.push(command);
+                        )
+                        .with_timeout(action.timeout);
+                        commands.push(command.labeled(svc_name));
                     }
                 }
             }
         }
 
-        return Instruction::basic(commands);
+        return Instruction::with_strategy(commands, self.strategy());
     }
 
     fn use_groups(&mut self, args: &[&str]) -> Instruction {
@@ -422,10 +1551,14 @@ impl Generator {
         for name in args {
             if let None = self.config.search_group(&name) {
                 let message = format!("--> unknown group [ {} ]", name);
-                return Instruction::echo(&message);
+                return Instruction::error(&message);
             }
         }
 
+        if self.dry_run {
+            return self.preview_groups(args);
+        }
+
         self.config.use_groups(args);
 
         if let Err(err) =
@@ -435,7 +1568,7 @@ impl Generator {
                 "--> cannot generate compose file [ {} ]: {}",
                 &self.compose_file, err
             );
-            return Instruction::echo(&message);
+            return Instruction::error(&message);
         }
 
         if let Err(err) = self.config.save(&self.config_file) {
@@ -443,14 +1576,47 @@ impl Generator {
                 "--> cannot save config file [ {} ]: {}",
                 &self.config_file, err
             );
-            return Instruction::echo(&message);
+            return Instruction::error(&message);
         }
 
-        let message = format!(
+        let mut lines = vec![format!(
             "--> saved compose: [ {} ] and config: [ {} ]",
             &self.compose_file, &self.config_file,
-        );
-        return Instruction::echo(&message);
+        )];
+        lines.extend(docker::volume_warnings(&self.config));
+        return Instruction::echo(&lines.join("\n"));
+    }
+
+    // Show what using the groups would write, without changing the compose
+    // file, the config file or the groups in use
+    fn preview_groups(&mut self, args: &[&str]) -> Instruction {
+        let using = self.config.using.take();
+        self.config.use_groups(args);
+        let result = docker::generate_compose_text(&self.config);
+        self.config.using = using;
+
+        let text = match result {
+            Ok(text) => text,
+            Err(err) => {
+                let message = format!(
+                    "--> cannot generate compose file [ {} ]: {}",
+                    &self.compose_file, err
+                );
+                return Instruction::error(&message);
+            }
+        };
+
+        let mut lines = vec![
+            format!("--> would save compose: [ {} ]", &self.compose_file),
+            String::from(text.trim_end()),
+            format!(
+                "--> would use [ {} ] in config: [ {} ]",
+                args.join(" "),
+                &self.config_file
+            ),
+        ];
+        lines.extend(docker::volume_warnings(&self.config));
+        return Instruction::echo(&lines.join("\n"));
     }
 
     fn do_dns(&self, args: &[&str]) -> Instruction {
@@ -474,12 +1640,12 @@ impl Generator {
                         };
                     } else {
                         let message = "--> docker machine config doesn't exist";
-                        commands.push(Command::echo(message));
+                        return Instruction::error(message);
                     }
                 }
                 _ => {
                     let message = format!("--> unsupported action {}", action);
-                    commands.push(Command::echo(&message));
+                    return Instruction::error(&message);
                 }
             };
             return Instruction::basic(commands);
@@ -488,6 +1654,81 @@ impl Generator {
         return Instruction::skip();
     }
 
+    fn custom_command(
+        &self,
+        custom: &CustomCommand,
+        args: &[&str],
+    ) -> Instruction {
+        let names: Vec<&str> = match &custom.args {
+            Some(names) => names.iter().map(String::as_ref).collect(),
+            None => Vec::new(),
+        };
+
+        if args.len() < names.len() {
+            let message = format!(
+                "--> missing argument [ {} ] for command [ {} ]",
+                names[args.len()],
+                custom.name
+            );
+            return Instruction::error(&message);
+        }
+
+        if args.len() > names.len() {
+            let message = format!(
+                "--> too many arguments for command [ {} ]",
+                custom.name
+            );
+            return Instruction::error(&message);
+        }
+
+        let service = match &custom.service {
+            Some(name) => match self.config.search_service(name) {
+                Some(service) => Some(service),
+                None => {
+                    let message = format!("--> unknown service [ {} ]", name);
+                    return Instruction::error(&message);
+                }
+            },
+            None => None,
+        };
+
+        // Steps get the arguments quoted again so spaces or `;` in a value
+        // can't break the command
+        let quoted: Vec<String> =
+            args.iter().map(|arg| util::quote_argument(arg)).collect();
+        let quoted: Vec<&str> = quoted.iter().map(String::as_ref).collect();
+
+        let dir = match &custom.dir {
+            Some(dir) => {
+                let dir = self.config.fill_patterns(dir, service);
+                util::fill_arguments(&dir, &names, args)
+            }
+            None => String::new(),
+        };
+
+        let mut commands: Vec<Command> = Vec::new();
+        for step in &custom.steps {
+            let raw = self.config.fill_patterns(step, service);
+            let raw = util::fill_arguments(&raw, &names, &quoted);
+            let command =
+                Command::new(&raw, &dir, true, false, false, None, true)
+                    .with_timeout(custom.timeout);
+            commands.push(command);
+        }
+
+        return Instruction::basic(commands);
+    }
+
+    // Strategy to run commands on multiple services or repositories: in
+    // parallel if the project allows it, otherwise one by one without
+    // stopping at the first failure
+    fn strategy(&self) -> Strategy {
+        match self.config.parallel {
+            Some(max) if max > 1 => return Strategy::Parallel(max),
+            _ => return Strategy::ContinueOnError,
+        }
+    }
+
     fn other(&self, raw: &str) -> Instruction {
         let command = Command::basic_hide(raw);
         return Instruction::basic(vec![command]);
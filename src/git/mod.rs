@@ -1,40 +1,443 @@
 #[cfg(test)]
 mod test;
 
-use super::cmd::Command;
+use super::cmd::{Command, CommandResult};
 use super::config::Repository;
+use super::decr;
+use super::util;
 
-pub fn clone_repository(repository: &Repository) -> Command {
-    let raw = format!("git clone {} {}", repository.remote, repository.local);
+use std::cmp;
+
+const SHORT_OID_LENGTH: usize = 7;
+const SECTION_PREFIX: &str = "### ";
+const COMMIT_PREFIX: &str = "#commit ";
+
+// Whether the command runs git, which may change the status of repositories
+pub fn runs_git(command: &Command) -> bool {
+    return command.raw.split_whitespace().any(|word| word == "git");
+}
+
+// Clone the repository into its local directory, only the given number of
+// latest commits and a single branch if provided
+pub fn clone_repository(
+    repository: &Repository,
+    depth: Option<u32>,
+    branch: Option<&str>,
+) -> Command {
+    let mut raw = String::from("git clone");
+    if let Some(depth) = depth {
+        raw.push_str(&format!(" --depth {}", depth));
+    }
+    if let Some(branch) = branch {
+        raw.push_str(&format!(" --branch {}", util::quote_argument(branch)));
+    }
+
+    let local = util::normalize_path(&repository.local);
+    raw.push_str(&format!(
+        " {} {}",
+        util::quote_argument(&repository.remote),
+        util::quote_argument(&local)
+    ));
     return Command::basic_show(&raw);
 }
 
-pub fn pull_repository(repo_dir: &str) -> Command {
-    return do_on_current_branch(repo_dir, "pull");
+// Store name of the current branch in $branch, or fail on a detached HEAD
+const CURRENT_BRANCH: &str = "branch=$(git symbolic-ref --quiet --short HEAD) \
+                              || { echo 'HEAD is detached, checkout a branch \
+                              first' >&2; false; }";
+
+// Pull current branch of the repository from its remote, using merge, rebase
+// or fast-forward only strategy if configured
+pub fn pull_repository(repository: &Repository) -> Result<Command, String> {
+    let option = match repository.pull.as_ref().map(String::as_ref) {
+        None => "",
+        Some("merge") => " --no-rebase",
+        Some("rebase") => " --rebase",
+        Some("ff-only") => " --ff-only",
+        Some(strategy) => {
+            return Err(format!(
+                "unknown pull strategy [ {} ] of [ {} ]",
+                strategy, repository.name
+            ));
+        }
+    };
+
+    let raw = format!(
+        "{} && git pull{} {} \"$branch\"",
+        CURRENT_BRANCH,
+        option,
+        util::quote_argument(repository.remote_name())
+    );
+    let dir = &repository.local;
+    return Ok(Command::new(&raw, dir, true, false, false, None, true));
+}
+
+// Push current branch of the repository to its remote, pushing to one of its
+// protected branches must be confirmed from the terminal
+pub fn push_repository(repository: &Repository) -> Command {
+    let remote = util::quote_argument(repository.remote_name());
+    let protected: Vec<String> = repository
+        .protected_branches()
+        .into_iter()
+        .map(util::quote_argument)
+        .collect();
+
+    let mut raw = String::from(CURRENT_BRANCH);
+    if !protected.is_empty() {
+        let confirm = format!(
+            " && case \"$branch\" in {}) \
+             printf 'push to protected branch %s of %s? [y/N] ' \
+             \"$branch\" {} > /dev/tty; read -r answer < /dev/tty; \
+             [ \"$answer\" = y ] || {{ echo 'push cancelled' >&2; false; }} ;; \
+             esac",
+            protected.join("|"),
+            remote
+        );
+        raw.push_str(&confirm);
+    }
+    raw.push_str(&format!(" && git push {} \"$branch\"", remote));
+
+    let dir = &repository.local;
+    return Command::new(&raw, dir, true, false, false, None, true);
+}
+
+// Fetch all branches of the repository from its remote, removing remote
+// tracking branches that no longer exist
+pub fn fetch_repository(repository: &Repository) -> Command {
+    let remote = util::quote_argument(repository.remote_name());
+    let raw = format!("git fetch --prune {}", remote);
+    let dir = &repository.local;
+    return Command::new(&raw, dir, true, false, false, None, true);
+}
+
+// Fast-forward the default branch of the repository to its remote without
+// checking it out, only merge when it is the current branch
+pub fn sync_repository(repository: &Repository) -> Command {
+    let remote = util::quote_argument(repository.remote_name());
+    let branch = util::quote_argument(repository.default_branch());
+    let raw = format!(
+        "git fetch {0} && \
+         if [ \"$(git symbolic-ref --quiet --short HEAD)\" = {1} ]; \
+         then git merge --ff-only {0}/{1}; \
+         else git fetch {0} {1}:{1}; fi",
+        remote, branch
+    );
+    let dir = &repository.local;
+    return Command::new(&raw, dir, true, false, false, None, true);
 }
 
-pub fn push_repository(repo_dir: &str) -> Command {
-    return do_on_current_branch(repo_dir, "push");
+// Delete local branches that are already merged into the remote default
+// branch, except the current, default and protected branches. They are
+// reachable from the remote, so forcing the deletion loses nothing. Branches
+// are listed by for-each-ref, which has no line for a detached HEAD
+pub fn prune_repository(repository: &Repository) -> Command {
+    let remote = util::quote_argument(repository.remote_name());
+    let branch = util::quote_argument(repository.default_branch());
+
+    let mut keep = vec![
+        String::from("\"$(git symbolic-ref --quiet --short HEAD)\""),
+        branch.to_owned(),
+    ];
+    for protected in repository.protected_branches() {
+        let protected = util::quote_argument(protected);
+        if !keep.contains(&protected) {
+            keep.push(protected);
+        }
+    }
+    let keep: Vec<String> = keep.iter().map(|b| format!("-e {}", b)).collect();
+
+    let raw = format!(
+        "git fetch --prune {0} && \
+         for b in $(git for-each-ref --format='%(refname:short)' \
+         --merged {0}/{1} refs/heads | grep -v -x {2}); \
+         do git branch -D \"$b\"; done",
+        remote,
+        branch,
+        keep.join(" ")
+    );
+    let dir = &repository.local;
+    return Command::new(&raw, dir, true, false, false, None, true);
 }
 
-pub fn do_on_current_branch(repo_dir: &str, action: &str) -> Command {
-    let branch = "git branch | grep -m1 \\* | grep -v \"master\" | \
-                  grep -v \"HEAD detached\" | cut -c 3-";
-    let raw = format!("git {} origin $({})", action, branch);
+// Switch repository to a branch, creating it first if create is true and it
+// doesn't exist yet; uncommitted changes stop the switch unless stash is true
+pub fn checkout_branch(
+    repo_dir: &str,
+    branch: &str,
+    create: bool,
+    stash: bool,
+) -> Command {
+    let name = util::quote_argument(branch);
+
+    let dirty = if stash {
+        let message = format!("turtle: before checkout {}", branch);
+        format!(
+            "git stash push --include-untracked -m {}",
+            util::quote_argument(&message)
+        )
+    } else {
+        String::from(
+            "echo 'uncommitted changes, commit them or use --stash' >&2 \
+             && false",
+        )
+    };
+
+    let switch = if create {
+        format!(
+            "if git show-ref --verify --quiet refs/heads/{0}; \
+             then git checkout {0}; else git checkout -b {0}; fi",
+            name
+        )
+    } else {
+        format!("git checkout {}", name)
+    };
+
+    let raw = format!(
+        "if [ -n \"$(git status --porcelain)\" ]; then {}; fi && {}",
+        dirty, switch
+    );
     return Command::new(&raw, repo_dir, true, false, false, None, true);
 }
 
-pub fn current_branch() -> Command {
-    let raw = "git branch";
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct GitStatus {
+    pub branch: String,
+    pub upstream: String,
+    pub detached: bool,
+    pub staged: usize,
+    pub unstaged: usize,
+    pub untracked: usize,
+    pub ahead: usize,
+    pub behind: usize,
+}
+
+impl GitStatus {
+    // Parse output of `git status --porcelain=v2 --branch`
+    pub fn parse(output: &str) -> Self {
+        let mut status = Self::default();
+        let mut oid = "";
 
-    let exec = |stdout: &str| -> (bool, String) {
-        for branch in stdout.lines() {
-            if branch.starts_with("*") {
-                return (true, String::from(branch));
+        for line in output.lines() {
+            let mut fields = line.split(' ');
+            match fields.next() {
+                Some("#") => match (fields.next(), fields.next()) {
+                    (Some("branch.oid"), Some(value)) => oid = value,
+                    (Some("branch.head"), Some("(detached)")) => {
+                        status.detached = true;
+                    }
+                    (Some("branch.head"), Some(value)) => {
+                        status.branch = String::from(value);
+                    }
+                    (Some("branch.upstream"), Some(value)) => {
+                        status.upstream = String::from(value);
+                    }
+                    (Some("branch.ab"), Some(ahead)) => {
+                        let behind = fields.next().unwrap_or("");
+                        status.ahead = parse_count(ahead, '+');
+                        status.behind = parse_count(behind, '-');
+                    }
+                    _ => (),
+                },
+                Some("1") | Some("2") | Some("u") => {
+                    let xy: Vec<char> =
+                        fields.next().unwrap_or("").chars().collect();
+                    if xy.len() == 2 {
+                        if xy[0] != '.' {
+                            status.staged += 1;
+                        }
+                        if xy[1] != '.' {
+                            status.unstaged += 1;
+                        }
+                    }
+                }
+                Some("?") => status.untracked += 1,
+                _ => (),
             }
         }
-        return (true, String::new());
+
+        if status.detached {
+            status.branch = oid.chars().take(SHORT_OID_LENGTH).collect();
+        }
+
+        return status;
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        return self.staged + self.unstaged + self.untracked > 0;
+    }
+}
+
+fn parse_count(text: &str, sign: char) -> usize {
+    return text.trim_start_matches(sign).parse().unwrap_or(0);
+}
+
+// Report branch, changed files and commits ahead or behind upstream of the
+// repository containing dir, in a single git call
+pub fn status_probe(dir: &str) -> Command {
+    let raw = format!(
+        "git -C {} status --porcelain=v2 --branch",
+        util::quote_argument(dir)
+    );
+    return Command::new(&raw, "", false, true, true, None, true);
+}
+
+// Show branch, changes, commits ahead or behind upstream and last commit of
+// repositories as a table, using a single shell call for all of them
+pub fn status_repositories(repositories: &[&Repository]) -> Command {
+    let mut scripts: Vec<String> = Vec::with_capacity(repositories.len());
+    for repository in repositories {
+        let dir = util::quote_argument(&util::normalize_path(&repository.local));
+        let script = format!(
+            "echo '{}{}'; \
+             git -C {} status --porcelain=v2 --branch 2>/dev/null && \
+             git -C {} log -1 --format='{}%h %s (%cr)' 2>/dev/null",
+            SECTION_PREFIX, repository.name, dir, dir, COMMIT_PREFIX
+        );
+        scripts.push(script);
+    }
+    let raw = format!("{}; true", scripts.join("; "));
+
+    let exec = |stdout: &str| -> CommandResult {
+        let rows = status_rows(stdout);
+        if rows.is_empty() {
+            let output = "--> no repositories";
+            println!("{}", output);
+            return CommandResult::ok(output);
+        }
+
+        let (mut wr, mut wb, mut wc, mut ws) = (10, 6, 7, 4);
+        for row in &rows {
+            wr = cmp::max(wr, row[0].chars().count());
+            wb = cmp::max(wb, row[1].chars().count());
+            wc = cmp::max(wc, row[2].chars().count() + 2);
+            ws = cmp::max(ws, row[3].chars().count());
+        }
+
+        let mut lines: Vec<String> = Vec::with_capacity(rows.len() + 1);
+        let header = format!(
+            "{:wr$}\t{:wb$}\t{:wc$}\t{:ws$}\t{}",
+            "REPOSITORY",
+            "BRANCH",
+            "CHANGES",
+            "SYNC",
+            "LAST COMMIT",
+            wr = wr,
+            wb = wb,
+            wc = wc,
+            ws = ws,
+        );
+        lines.push(header);
+
+        for row in &rows {
+            let mark = match row[2].as_ref() {
+                "clean" => decr::green("✓"),
+                "not cloned" => decr::red("𐄂"),
+                _ => decr::yellow("±"),
+            };
+
+            // Colors take no room on screen, pad the text before adding them
+            let changes = format!("{:w$}", row[2], w = wc - 2);
+            let line = format!(
+                "{:wr$}\t{:wb$}\t{} {}\t{:ws$}\t{}",
+                row[0],
+                row[1],
+                mark,
+                changes,
+                row[3],
+                row[4],
+                wr = wr,
+                wb = wb,
+                ws = ws,
+            );
+            lines.push(line);
+        }
+
+        let output = lines.join("\n");
+        println!("{}", output);
+
+        return CommandResult::ok(&output);
     };
 
-    return Command::new(raw, "", false, true, true, Some(Box::new(exec)), true);
+    return Command::new(&raw, "", false, false, true, Some(Box::new(exec)), true);
+}
+
+// Split output of status_repositories into rows of repository, branch,
+// changes, sync state with upstream and last commit
+fn status_rows(stdout: &str) -> Vec<[String; 5]> {
+    let mut rows: Vec<[String; 5]> = Vec::new();
+    let mut sections: Vec<(&str, Vec<&str>)> = Vec::new();
+
+    for line in stdout.lines() {
+        if line.starts_with(SECTION_PREFIX) {
+            let name = line.trim_start_matches(SECTION_PREFIX);
+            sections.push((name, Vec::new()));
+        } else if let Some((_, lines)) = sections.last_mut() {
+            lines.push(line);
+        }
+    }
+
+    for (name, lines) in sections {
+        if lines.is_empty() {
+            rows.push([
+                String::from(name),
+                String::from("-"),
+                String::from("not cloned"),
+                String::from("-"),
+                String::from("-"),
+            ]);
+            continue;
+        }
+
+        let mut commit = "";
+        let mut status_lines: Vec<&str> = Vec::new();
+        for line in lines {
+            if line.starts_with(COMMIT_PREFIX) {
+                commit = line.trim_start_matches(COMMIT_PREFIX);
+            } else {
+                status_lines.push(line);
+            }
+        }
+
+        let status = GitStatus::parse(&status_lines.join("\n"));
+        let branch = if status.detached {
+            format!("(detached {})", status.branch)
+        } else {
+            status.branch.to_owned()
+        };
+
+        let mut changes: Vec<String> = Vec::new();
+        for (symbol, count) in &[
+            ("+", status.staged),
+            ("!", status.unstaged),
+            ("?", status.untracked),
+        ] {
+            if *count > 0 {
+                changes.push(format!("{}{}", symbol, count));
+            }
+        }
+        let changes = if changes.is_empty() {
+            String::from("clean")
+        } else {
+            changes.join(" ")
+        };
+
+        let sync = if status.upstream.is_empty() {
+            String::from("-")
+        } else if status.ahead == 0 && status.behind == 0 {
+            String::from("=")
+        } else {
+            format!("↑{} ↓{}", status.ahead, status.behind)
+        };
+
+        let commit = if commit.is_empty() { "-" } else { commit };
+        rows.push([
+            String::from(name),
+            branch,
+            changes,
+            sync,
+            String::from(commit),
+        ]);
+    }
+
+    return rows;
 }
@@ -1,57 +1,305 @@
 use super::*;
 use crate::config::Repository;
 
+#[test]
+fn test_runs_git() {
+    assert!(runs_git(&Command::basic_show("git fetch --prune origin")));
+    assert!(runs_git(&Command::basic_show("cd /tmp && git status")));
+    assert!(!runs_git(&Command::basic_show("cargo build")));
+    assert!(!runs_git(&Command::basic_show("ls .gitignore")));
+}
+
 #[test]
 fn test_clone_repository() {
     let repository = Repository {
         name: String::from("turtle"),
         remote: String::from("git@gitlab.com:phamlequang/turtle.git"),
         local: String::from("~/projects/turtle"),
+        ..Default::default()
     };
 
-    let command = clone_repository(&repository);
-    let raw = format!("git clone {} {}", repository.remote, repository.local);
+    let command = clone_repository(&repository, None, None);
+    let raw = format!(
+        "git clone {} {}",
+        repository.remote,
+        util::normalize_path(&repository.local)
+    );
 
     let expect = Command::new(&raw, "", true, false, false, None, true);
     assert_eq!(command, expect);
 }
 
+#[test]
+fn test_clone_repository_with_depth_and_branch() {
+    let repository = Repository {
+        name: String::from("turtle"),
+        remote: String::from("git@gitlab.com:phamlequang/turtle.git"),
+        local: String::from("/projects/my turtle"),
+        ..Default::default()
+    };
+
+    let command = clone_repository(&repository, Some(1), Some("release"));
+    let raw = "git clone --depth 1 --branch release \
+               git@gitlab.com:phamlequang/turtle.git '/projects/my turtle'";
+    assert_eq!(command.raw, raw);
+}
+
+fn sample_repository() -> Repository {
+    return Repository {
+        name: String::from("turtle"),
+        remote: String::from("git@gitlab.com:phamlequang/turtle.git"),
+        local: String::from("~/projects/turtle"),
+        ..Default::default()
+    };
+}
+
 #[test]
 fn test_pull_repository() {
-    let repo_dir = "~/projects/turtle";
-    let raw = "git pull origin $(git branch | grep -m1 \\* | \
-               grep -v \"master\" | grep -v \"HEAD detached\" | cut -c 3-)";
+    let repository = sample_repository();
+    let raw = "branch=$(git symbolic-ref --quiet --short HEAD) || \
+               { echo 'HEAD is detached, checkout a branch first' >&2; \
+               false; } && git pull origin \"$branch\"";
 
-    let command = pull_repository(repo_dir);
-    let expect = Command::new(&raw, repo_dir, true, false, false, None, true);
+    let command = pull_repository(&repository).unwrap();
+    let dir = "~/projects/turtle";
+    let expect = Command::new(&raw, dir, true, false, false, None, true);
     assert_eq!(command, expect);
 }
 
+#[test]
+fn test_pull_repository_with_strategy() {
+    let mut repository = sample_repository();
+    repository.remote_name = Some(String::from("upstream"));
+
+    repository.pull = Some(String::from("ff-only"));
+    let command = pull_repository(&repository).unwrap();
+    assert!(command.raw.ends_with("git pull --ff-only upstream \"$branch\""));
+
+    repository.pull = Some(String::from("rebase"));
+    let command = pull_repository(&repository).unwrap();
+    assert!(command.raw.ends_with("git pull --rebase upstream \"$branch\""));
+
+    repository.pull = Some(String::from("squash"));
+    let result = pull_repository(&repository);
+    let message = "unknown pull strategy [ squash ] of [ turtle ]";
+    assert_eq!(result.unwrap_err(), message);
+}
+
 #[test]
 fn test_push_repository() {
-    let repo_dir = "~/projects/turtle";
-    let raw = "git push origin $(git branch | grep -m1 \\* | \
-               grep -v \"master\" | grep -v \"HEAD detached\" | cut -c 3-)";
+    let repository = sample_repository();
+    let raw = "branch=$(git symbolic-ref --quiet --short HEAD) || \
+               { echo 'HEAD is detached, checkout a branch first' >&2; \
+               false; } && case \"$branch\" in master) \
+               printf 'push to protected branch %s of %s? [y/N] ' \
+               \"$branch\" origin > /dev/tty; read -r answer < /dev/tty; \
+               [ \"$answer\" = y ] || { echo 'push cancelled' >&2; false; } ;; \
+               esac && git push origin \"$branch\"";
 
-    let command = push_repository(repo_dir);
-    let expect = Command::new(&raw, repo_dir, true, false, false, None, true);
+    let command = push_repository(&repository);
+    let dir = "~/projects/turtle";
+    let expect = Command::new(&raw, dir, true, false, false, None, true);
     assert_eq!(command, expect);
 }
 
 #[test]
-fn test_current_branch() {
-    let command = current_branch();
+fn test_push_repository_protected_branches() {
+    let mut repository = sample_repository();
+
+    repository.protected_branches =
+        Some(vec![String::from("main"), String::from("release/*")]);
+    let command = push_repository(&repository);
+    assert!(command.raw.contains("case \"$branch\" in main|'release/*')"));
+
+    repository.protected_branches = Some(Vec::new());
+    let command = push_repository(&repository);
+    assert!(!command.raw.contains("case"));
+    assert!(command.raw.ends_with("&& git push origin \"$branch\""));
+}
+
+#[test]
+fn test_status_probe() {
+    let command = status_probe("/projects/my turtle");
 
-    assert_eq!(command.raw, "git branch");
+    let raw = "git -C '/projects/my turtle' status --porcelain=v2 --branch";
+    assert_eq!(command.raw, raw);
     assert!(command.dir.is_empty());
     assert!(!command.show);
     assert!(command.silent);
     assert!(command.pipe);
+    assert!(command.then.is_none());
+}
+
+#[test]
+fn test_fetch_repository() {
+    let repository = sample_repository();
+    let command = fetch_repository(&repository);
+
+    let raw = "git fetch --prune origin";
+    let dir = "~/projects/turtle";
+    let expect = Command::new(raw, dir, true, false, false, None, true);
+    assert_eq!(command, expect);
+}
+
+#[test]
+fn test_sync_repository() {
+    let mut repository = sample_repository();
+    repository.remote_name = Some(String::from("upstream"));
+    repository.default_branch = Some(String::from("main"));
+
+    let command = sync_repository(&repository);
+    let raw = "git fetch upstream && \
+               if [ \"$(git symbolic-ref --quiet --short HEAD)\" = main ]; \
+               then git merge --ff-only upstream/main; \
+               else git fetch upstream main:main; fi";
+
+    let dir = "~/projects/turtle";
+    let expect = Command::new(raw, dir, true, false, false, None, true);
+    assert_eq!(command, expect);
+}
+
+#[test]
+fn test_prune_repository() {
+    let mut repository = sample_repository();
+    repository.protected_branches =
+        Some(vec![String::from("master"), String::from("release")]);
+
+    let command = prune_repository(&repository);
+    let raw = "git fetch --prune origin && \
+               for b in $(git for-each-ref --format='%(refname:short)' \
+               --merged origin/master refs/heads | grep -v -x \
+               -e \"$(git symbolic-ref --quiet --short HEAD)\" \
+               -e master -e release); do git branch -D \"$b\"; done";
+
+    let dir = "~/projects/turtle";
+    let expect = Command::new(raw, dir, true, false, false, None, true);
+    assert_eq!(command, expect);
+}
+
+#[test]
+fn test_checkout_branch() {
+    let command = checkout_branch("~/projects/turtle", "feature", false, false);
+    let raw = "if [ -n \"$(git status --porcelain)\" ]; then \
+               echo 'uncommitted changes, commit them or use --stash' >&2 \
+               && false; fi && git checkout feature";
+
+    let expect =
+        Command::new(raw, "~/projects/turtle", true, false, false, None, true);
+    assert_eq!(command, expect);
+}
+
+#[test]
+fn test_checkout_new_branch_with_stash() {
+    let command = checkout_branch("~/projects/turtle", "feat x", true, true);
+    let raw = "if [ -n \"$(git status --porcelain)\" ]; then \
+               git stash push --include-untracked \
+               -m 'turtle: before checkout feat x'; fi && \
+               if git show-ref --verify --quiet refs/heads/'feat x'; \
+               then git checkout 'feat x'; else git checkout -b 'feat x'; fi";
+
+    let expect =
+        Command::new(raw, "~/projects/turtle", true, false, false, None, true);
+    assert_eq!(command, expect);
+}
+
+#[test]
+fn test_parse_git_status() {
+    let output = "# branch.oid 4f2c1d9e8a7b6c5d4e3f2a1b0c9d8e7f6a5b4c3d\n\
+                  # branch.head feature\n\
+                  # branch.upstream origin/feature\n\
+                  # branch.ab +2 -1\n\
+                  1 M. N... 100644 100644 100644 1a2b 3c4d src/lib.rs\n\
+                  1 .M N... 100644 100644 100644 1a2b 3c4d src/main.rs\n\
+                  1 MM N... 100644 100644 100644 1a2b 3c4d README.md\n\
+                  2 R. N... 100644 100644 100644 1a2b 3c4d R100 a.rs\tb.rs\n\
+                  ? notes.txt\n\
+                  ? todo.txt\n";
+
+    let status = GitStatus::parse(output);
+    let expect = GitStatus {
+        branch: String::from("feature"),
+        upstream: String::from("origin/feature"),
+        detached: false,
+        staged: 3,
+        unstaged: 2,
+        untracked: 2,
+        ahead: 2,
+        behind: 1,
+    };
+    assert_eq!(status, expect);
+    assert!(status.is_dirty());
+}
+
+#[test]
+fn test_parse_git_status_detached() {
+    let output = "# branch.oid 4f2c1d9e8a7b6c5d4e3f2a1b0c9d8e7f6a5b4c3d\n\
+                  # branch.head (detached)\n";
+
+    let status = GitStatus::parse(output);
+    assert!(status.detached);
+    assert_eq!(status.branch, "4f2c1d9");
+    assert_eq!(status.ahead, 0);
+    assert!(!status.is_dirty());
+}
+
+#[test]
+fn test_status_repositories() {
+    let flowers = Repository {
+        name: String::from("flowers"),
+        remote: String::from("git@gitlab.com:phamlequang/flowers.git"),
+        local: String::from("/projects/flowers"),
+        ..Default::default()
+    };
+    let turtle = Repository {
+        name: String::from("turtle"),
+        remote: String::from("git@gitlab.com:phamlequang/turtle.git"),
+        local: String::from("/projects/turtle"),
+        ..Default::default()
+    };
+
+    let command = status_repositories(&[&flowers, &turtle]);
+    assert!(command.raw.starts_with(
+        "echo '### flowers'; git -C /projects/flowers status \
+         --porcelain=v2 --branch 2>/dev/null && git -C /projects/flowers \
+         log -1 --format='#commit %h %s (%cr)' 2>/dev/null; \
+         echo '### turtle';"
+    ));
+    assert!(command.raw.ends_with("; true"));
+    assert!(!command.show);
+    assert!(command.pipe);
     assert!(command.then.is_some());
-    assert!(command.back);
 
     let exec = command.then.unwrap();
-    let (success, branch) = exec("master\n* feature\n");
-    assert!(success);
-    assert_eq!(branch, "* feature");
+    let stdout = "### flowers\n\
+                  # branch.oid 4f2c1d9e8a7b6c5d4e3f2a1b0c9d8e7f6a5b4c3d\n\
+                  # branch.head feature\n\
+                  # branch.upstream origin/feature\n\
+                  # branch.ab +2 -1\n\
+                  1 .M N... 100644 100644 100644 1a2b 3c4d src/main.rs\n\
+                  ? notes.txt\n\
+                  #commit 4f2c1d9 Add lotus (2 hours ago)\n\
+                  ### turtle\n\
+                  # branch.oid 9e8a7b6c5d4e3f2a1b0c9d8e7f6a5b4c3d4f2c1d\n\
+                  # branch.head master\n\
+                  # branch.upstream origin/master\n\
+                  # branch.ab +0 -0\n\
+                  #commit 9e8a7b6 Fix prompt (3 days ago)\n\
+                  ### tree\n";
+    let expect = format!(
+        "REPOSITORY\tBRANCH \tCHANGES     \tSYNC \tLAST COMMIT\n\
+         flowers   \tfeature\t{} !1 ?1     \t↑2 ↓1\t4f2c1d9 Add lotus (2 hours ago)\n\
+         turtle    \tmaster \t{} clean     \t=    \t9e8a7b6 Fix prompt (3 days ago)\n\
+         tree      \t-      \t{} not cloned\t-    \t-",
+        decr::yellow("±"),
+        decr::green("✓"),
+        decr::red("𐄂"),
+    );
+
+    let result = exec(stdout);
+    assert!(result.success());
+    assert_eq!(result.stdout, expect);
+
+    let result = exec("");
+    assert!(result.success());
+    assert_eq!(result.stdout, "--> no repositories");
 }
@@ -5,10 +5,25 @@ use std::cmp::PartialEq;
 
 use super::cmd::Command;
 
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Strategy {
+    // Run commands one by one, stop immediately in case of failure
+    Sequential,
+    // Run commands one by one, keep going in case of failure
+    ContinueOnError,
+    // Run at most the given number of commands at the same time
+    Parallel(usize),
+}
+
 #[derive(Debug)]
 pub struct Instruction {
     pub commands: Vec<Command>,
     pub should_terminate: bool,
+    pub dry_run: bool,
+    pub strategy: Strategy,
+    // The instruction is invalid, such as an unknown command or a missing
+    // argument, its commands only tell the user what is wrong
+    pub error: bool,
 }
 
 impl Instruction {
@@ -16,6 +31,9 @@ impl Instruction {
         return Self {
             commands,
             should_terminate,
+            dry_run: false,
+            strategy: Strategy::Sequential,
+            error: false,
         };
     }
 
@@ -23,6 +41,12 @@ impl Instruction {
         return Self::new(commands, false);
     }
 
+    pub fn with_strategy(commands: Vec<Command>, strategy: Strategy) -> Self {
+        let mut instruction = Self::basic(commands);
+        instruction.strategy = strategy;
+        return instruction;
+    }
+
     pub fn skip() -> Self {
         return Self::basic(Vec::new());
     }
@@ -36,12 +60,23 @@ impl Instruction {
         let command = Command::echo(message);
         return Self::basic(vec![command]);
     }
+
+    // Echo an error message, running the instruction then fails
+    pub fn error(message: &str) -> Self {
+        let mut instruction = Self::echo(message);
+        instruction.error = true;
+        return instruction;
+    }
 }
 
 impl PartialEq for Instruction {
     // Check if 2 instructions are identical or not
     fn eq(&self, other: &Self) -> bool {
-        if self.should_terminate != other.should_terminate {
+        if self.should_terminate != other.should_terminate
+            || self.dry_run != other.dry_run
+            || self.strategy != other.strategy
+            || self.error != other.error
+        {
             return false;
         }
 
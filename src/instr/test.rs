@@ -5,6 +5,19 @@ fn test_skip() {
     let instruction = Instruction::skip();
     assert!(instruction.commands.is_empty());
     assert!(!instruction.should_terminate);
+    assert!(!instruction.dry_run);
+    assert!(!instruction.error);
+    assert_eq!(instruction.strategy, Strategy::Sequential);
+}
+
+#[test]
+fn test_with_strategy() {
+    let command = Command::basic_show("cargo build");
+    let instruction =
+        Instruction::with_strategy(vec![command], Strategy::Parallel(4));
+    assert_eq!(instruction.commands.len(), 1);
+    assert_eq!(instruction.strategy, Strategy::Parallel(4));
+    assert_ne!(instruction, Instruction::basic(vec![]));
 }
 
 #[test]
@@ -32,3 +45,19 @@ fn test_echo() {
     let expect = Command::echo(message);
     assert_eq!(&commands[0], &expect);
 }
+
+#[test]
+fn test_error() {
+    let message = "--> unknown command [ abc ]";
+
+    let instruction = Instruction::error(message);
+    assert!(instruction.error);
+    assert!(!instruction.should_terminate);
+    assert_ne!(instruction, Instruction::echo(message));
+
+    let commands = &instruction.commands;
+    assert_eq!(commands.len(), 1);
+
+    let expect = Command::echo(message);
+    assert_eq!(&commands[0], &expect);
+}
@@ -1,5 +1,7 @@
+pub mod args;
 pub mod brew;
 pub mod cmd;
+pub mod compose;
 pub mod config;
 pub mod decr;
 pub mod dns;
@@ -8,39 +10,166 @@ pub mod gen;
 pub mod git;
 pub mod instr;
 pub mod prompt;
+pub mod registry;
 pub mod shell;
 pub mod util;
 
 use ctrlc;
+use std::process;
+use std::sync::Once;
+use std::time::Instant;
+
+use args::Arguments;
+use cmd::{CommandResult, ErrorKind};
 
 // Run turtle shell for a specific project
-pub fn run(project: &str) {
-    let config_dir = util::default_config_directory();
-    let history_file = util::history_file(&config_dir, project);
+pub fn run(args: &Arguments) {
+    let history_file = util::history_file(&args.config_dir, &args.project);
 
-    let mut generator = match gen::Generator::new(&config_dir, project) {
-        Ok(gnrt) => gnrt,
-        Err(err) => {
-            println!("--> cannot create generator: {}", err);
-            return;
-        }
+    let mut generator = match new_generator(args) {
+        Some(gnrt) => gnrt,
+        None => return,
     };
 
-    let mut prompt = prompt::Prompt::new();
+    let mut prompt = prompt::Prompt::new(generator.config());
     prompt.load_history(&history_file);
-    prompt.clear_screen();
+    if !args.no_clear {
+        prompt.clear_screen();
+    }
 
-    ctrlc::set_handler(|| ()).expect("--> cannot set ctrl-c handler");
+    set_interrupt_handler();
 
+    let mut last = CommandResult::ok("");
     let mut stop = false;
     while !stop {
-        let line = prompt.read_line();
+        let line = prompt.read_line(generator.config(), &last);
 
+        let start = Instant::now();
         let instruction = generator.generate_instruction(&line);
-        shell::run_instruction(&instruction);
+        last = shell::run_instruction(&instruction);
+        last.duration = start.elapsed();
+
+        if instruction.commands.iter().any(git::runs_git) {
+            prompt.refresh_git_status();
+        }
 
         stop = instruction.should_terminate;
     }
 
     prompt.save_history(&history_file);
 }
+
+// Run a list of turtle instructions without prompting, stop at the first
+// failure, return its result or the result of the last instruction
+pub fn execute(args: &Arguments, lines: &[String]) -> CommandResult {
+    set_interrupt_handler();
+
+    let mut generator = match new_generator(args) {
+        Some(gnrt) => gnrt,
+        None => {
+            let message = "cannot create generator";
+            return CommandResult::fail(ErrorKind::Execute, message);
+        }
+    };
+
+    let mut result = CommandResult::ok("");
+    for line in lines {
+        let instruction = generator.generate_instruction(line);
+        result = shell::run_instruction(&instruction);
+
+        if !result.success() || instruction.should_terminate {
+            break;
+        }
+    }
+
+    return result;
+}
+
+// Run all instructions in a script file, one per line
+pub fn execute_script(args: &Arguments, file_path: &str) -> CommandResult {
+    match util::load_script(file_path) {
+        Ok(lines) => return execute(args, &lines),
+        Err(err) => {
+            let message =
+                format!("cannot load script [ {} ]: {}", file_path, err);
+            println!("--> {}", message);
+            return CommandResult::fail(ErrorKind::Execute, &message);
+        }
+    }
+}
+
+// Run instructions given by command line arguments, then the script file
+pub fn execute_batch(args: &Arguments) -> CommandResult {
+    let mut result = CommandResult::ok("");
+
+    if !args.commands.is_empty() {
+        result = execute(args, &args.commands);
+        if !result.success() {
+            return result;
+        }
+    }
+
+    if let Some(file) = &args.script {
+        result = execute_script(args, file);
+    }
+
+    return result;
+}
+
+// Print name of all projects that have a config file in config directory
+pub fn list_projects(config_dir: &str) -> bool {
+    match util::list_projects(config_dir) {
+        Ok(projects) => {
+            for project in projects {
+                println!("{}", project);
+            }
+            return true;
+        }
+        Err(err) => {
+            println!("--> cannot list projects in [ {} ]: {}", config_dir, err);
+            return false;
+        }
+    }
+}
+
+// Exit the current process with a status code telling whether turtle
+// instructions succeeded or not
+pub fn exit(success: bool) -> ! {
+    process::exit(if success { 0 } else { 1 });
+}
+
+// Exit the current process with the exit code of a command result
+pub fn exit_with(result: &CommandResult) -> ! {
+    process::exit(result.exit_code());
+}
+
+// Pressing ctrl-c cancels the running commands instead of killing turtle,
+// the handler can only be set once per process
+fn set_interrupt_handler() {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| {
+        ctrlc::set_handler(shell::cancel)
+            .expect("--> cannot set ctrl-c handler");
+    });
+}
+
+fn new_generator(args: &Arguments) -> Option<gen::Generator> {
+    if args.verbose {
+        println!(
+            "--> project [ {} ] in config directory [ {} ]",
+            args.project, args.config_dir
+        );
+    }
+
+    match gen::Generator::new(&args.config_dir, &args.project) {
+        Ok(mut gnrt) => {
+            gnrt.set_dry_run(args.dry_run);
+            gnrt.set_verbose(args.verbose);
+            return Some(gnrt);
+        }
+        Err(err) => {
+            println!("--> cannot create generator: {}", err);
+            return None;
+        }
+    }
+}
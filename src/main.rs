@@ -1,17 +1,36 @@
 use turtle;
+use turtle::args::{self, Arguments};
 
 use std::env;
 
-const DEFAULT_PROJECT: &str = "default";
-
 fn main() {
-    let args: Vec<_> = env::args().collect();
+    let args: Vec<String> = env::args().skip(1).collect();
 
-    let project = if args.len() > 1 {
-        args[1].to_lowercase()
-    } else {
-        String::from(DEFAULT_PROJECT)
+    let args = match Arguments::parse(&args) {
+        Ok(args) => args,
+        Err(err) => {
+            println!("--> {}\n{}", err, args::USAGE);
+            turtle::exit(false);
+        }
     };
 
-    turtle::run(&project);
+    if args.help {
+        println!("{}", args::USAGE);
+        turtle::exit(true);
+    }
+
+    if args.version {
+        println!("turtle {}", env!("CARGO_PKG_VERSION"));
+        turtle::exit(true);
+    }
+
+    if args.list_projects {
+        turtle::exit(turtle::list_projects(&args.config_dir));
+    }
+
+    if args.is_batch() {
+        turtle::exit_with(&turtle::execute_batch(&args));
+    }
+
+    turtle::run(&args);
 }
@@ -1,41 +1,113 @@
 #[cfg(test)]
 mod test;
 
+use super::cmd::CommandResult;
+use super::config;
+use super::decr;
+use super::git::{self, GitStatus};
+use super::gen::COMMANDS;
+use super::registry::{self, Completion};
 use super::shell;
 use super::util;
 
 use rustyline::completion::{Completer, FilenameCompleter, Pair};
-use rustyline::config::OutputStreamType;
+use rustyline::config::{Builder, OutputStreamType};
 use rustyline::error::ReadlineError;
 use rustyline::highlight::Highlighter;
 use rustyline::hint::Hinter;
-use rustyline::{Cmd, CompletionType, Config, EditMode, Editor, Helper, KeyPress};
-use termion::{color, color::Cyan, color::Green, color::Yellow, style};
+use rustyline::{
+    Anchor, At, Cmd, CompletionType, Config, EditMode, Editor, Helper,
+    KeyPress, Movement, Word,
+};
+use std::borrow::Cow::{self, Owned};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use termion::{color, style};
 
 const CURRENT_DIR_MAX_LENGTH: usize = 20;
+const MIN_DURATION_SECONDS: f64 = 1.0;
+const DEFAULT_TEMPLATE: &str = "{bold}{cyan} ~ {dir} \
+                                {green}{branch}{red}{dirty} \
+                                {yellow}{ahead}{behind} ➜ {reset}";
+const GIT_STAMP_FILES: &[&str] = &["HEAD", "index", "FETCH_HEAD"];
+const GIT_SEGMENTS: &[&str] = &[
+    "branch",
+    "dirty",
+    "staged",
+    "unstaged",
+    "untracked",
+    "ahead",
+    "behind",
+];
 
 pub struct Prompt {
     editor: Editor<PromptHelper>,
+    probe: GitProbe,
+    command: Option<String>,
 }
 
 impl Prompt {
-    pub fn new() -> Self {
-        let config = Config::builder()
+    pub fn new(cfg: &config::Config) -> Self {
+        let settings = cfg.prompt.as_ref();
+
+        let mut builder = Config::builder()
             .history_ignore_space(true)
             .completion_type(CompletionType::List)
             .edit_mode(EditMode::Emacs)
-            .output_stream(OutputStreamType::Stdout)
-            .build();
+            .output_stream(OutputStreamType::Stdout);
+        if let Some(settings) = settings {
+            builder = configure(builder, settings);
+        }
 
         let helper = PromptHelper {
             completer: FilenameCompleter::new(),
+            vocabulary: Vocabulary::new(cfg),
+            history: Vec::new(),
         };
 
-        let mut editor = Editor::with_config(config);
+        let mut editor = Editor::with_config(builder.build());
         editor.set_helper(Some(helper));
         editor.bind_sequence(KeyPress::ShiftDown, Cmd::HistorySearchForward);
         editor.bind_sequence(KeyPress::ShiftUp, Cmd::HistorySearchBackward);
-        return Self { editor };
+
+        let mut command = None;
+        if let Some(bindings) = settings.and_then(|p| p.bindings.as_ref()) {
+            for binding in bindings {
+                let (key, cmd) = match key_binding(binding) {
+                    Ok(pair) => pair,
+                    Err(message) => {
+                        println!("--> {}", message);
+                        continue;
+                    }
+                };
+
+                if let Some(text) = &binding.command {
+                    if command.is_some() {
+                        println!(
+                            "--> key [ {} ] is ignored, another runs a command",
+                            binding.key
+                        );
+                        continue;
+                    }
+                    // The command key interrupts readline, so ctrl-c only
+                    // clears the line to tell them apart
+                    command = Some(text.clone());
+                    editor.bind_sequence(
+                        KeyPress::Ctrl('C'),
+                        Cmd::Kill(Movement::WholeLine),
+                    );
+                }
+                editor.bind_sequence(key, cmd);
+            }
+        }
+
+        return Self {
+            editor,
+            probe: GitProbe::new(),
+            command,
+        };
     }
 
     pub fn load_history(&mut self, history_file: &str) {
@@ -43,6 +115,17 @@ impl Prompt {
             if let Err(err) = self.editor.load_history(history_file) {
                 println!("--> cannot load history from [ {} ]: {}", history_file, err);
             }
+            self.sync_history();
+        }
+    }
+
+    // Hints can't reach the editor history, so the helper keeps a copy of it,
+    // limited to the same size and without ignored entries
+    fn sync_history(&mut self) {
+        let history = self.editor.history();
+        let entries: Vec<String> = history.iter().cloned().collect();
+        if let Some(helper) = self.editor.helper_mut() {
+            helper.history = entries;
         }
     }
 
@@ -52,57 +135,658 @@ impl Prompt {
         }
     }
 
+    // Status of repositories may have changed after running git commands
+    pub fn refresh_git_status(&mut self) {
+        self.probe.clear();
+    }
+
     pub fn clear_screen(&self) {
         print!("{}{}", termion::clear::All, termion::cursor::Goto(1, 1));
     }
 
-    // Prompt current directory and read a new line from stdin
-    pub fn read_line(&mut self) -> String {
-        let message = self.message();
+    // Prompt current status and read a new line from stdin
+    pub fn read_line(
+        &mut self,
+        cfg: &config::Config,
+        last: &CommandResult,
+    ) -> String {
+        let message = self.message(cfg, last);
+        let result = self.editor.readline(&message);
+        return self.accepted_line(result);
+    }
 
-        match self.editor.readline(&message) {
+    // Readline can't replace and accept the line with a single key, so the
+    // key bound to a command interrupts it and the command runs instead
+    fn accepted_line(&mut self, result: rustyline::Result<String>) -> String {
+        match result {
             Ok(line) => {
-                self.editor.add_history_entry(line.as_ref());
+                if self.editor.add_history_entry(line.as_ref()) {
+                    self.sync_history();
+                }
                 return line;
             }
+            Err(ReadlineError::Interrupted) => {
+                return self.command.clone().unwrap_or_default();
+            }
             Err(_) => return String::new(),
         }
     }
 
-    pub fn message(&self) -> String {
-        let dir = util::current_directory_shortened(CURRENT_DIR_MAX_LENGTH);
-        let branch = shell::current_git_branch();
+    // Render the prompt template of the project, or the default one
+    pub fn message(
+        &mut self,
+        cfg: &config::Config,
+        last: &CommandResult,
+    ) -> String {
+        let settings = cfg.prompt.as_ref();
+        let template = settings
+            .and_then(|p| p.template.as_ref())
+            .map_or(DEFAULT_TEMPLATE, String::as_ref);
+        let max_len = settings
+            .and_then(|p| p.dir_max_length)
+            .unwrap_or(CURRENT_DIR_MAX_LENGTH);
+
+        // Only look for git status if the template shows it
+        let uses_git = GIT_SEGMENTS
+            .iter()
+            .any(|name| template.contains(&format!("{{{}}}", name)));
+        let dir = util::current_directory();
+        let status = if uses_git { self.probe.status(&dir) } else { None };
+
+        return render_template(template, |name| match name {
+            "dir" => Some(util::current_directory_shortened(max_len)),
+            _ if GIT_SEGMENTS.contains(&name) => {
+                Some(git_segment(name, status.as_ref()))
+            }
+            _ => segment(name, cfg, last),
+        });
+    }
+}
+
+// Git status of repositories, cached until their HEAD, index or FETCH_HEAD
+// file changes, so it is cheap to ask for it on every prompt. Edits of the
+// working tree don't touch these files either, so it is also cleared after
+// git commands run
+struct GitProbe {
+    cache: HashMap<PathBuf, (Vec<Option<SystemTime>>, GitStatus)>,
+}
+
+impl GitProbe {
+    fn new() -> Self {
+        return Self {
+            cache: HashMap::new(),
+        };
+    }
+
+    fn clear(&mut self) {
+        self.cache.clear();
+    }
+
+    // Return git status of the repository containing dir, or None if it
+    // isn't in a git repository
+    fn status(&mut self, dir: &str) -> Option<GitStatus> {
+        let git_dir = util::find_git_directory(dir)?;
+
+        if let Some((stamp, status)) = self.cache.get(&git_dir) {
+            if *stamp == git_stamp(&git_dir) {
+                return Some(status.clone());
+            }
+        }
+
+        let result = shell::run_command(&git::status_probe(dir));
+        if !result.success() {
+            return None;
+        }
+
+        // git status may refresh the index, so stamp after running it
+        let status = GitStatus::parse(&result.stdout);
+        let stamp = git_stamp(&git_dir);
+        self.cache.insert(git_dir, (stamp, status.clone()));
+        return Some(status);
+    }
+}
+
+fn git_stamp(git_dir: &Path) -> Vec<Option<SystemTime>> {
+    return GIT_STAMP_FILES
+        .iter()
+        .map(|file| {
+            let metadata = fs::metadata(git_dir.join(file)).ok()?;
+            return metadata.modified().ok();
+        })
+        .collect();
+}
+
+// Apply edit mode, completion type and history settings of the project,
+// invalid values are reported and ignored
+fn configure(builder: Builder, settings: &config::Prompt) -> Builder {
+    let mut builder = builder;
+
+    if let Some(mode) = &settings.edit_mode {
+        match mode.as_ref() {
+            "emacs" => builder = builder.edit_mode(EditMode::Emacs),
+            "vi" => builder = builder.edit_mode(EditMode::Vi),
+            _ => println!("--> unknown edit mode [ {} ] of prompt", mode),
+        }
+    }
+
+    if let Some(kind) = &settings.completion_type {
+        match kind.as_ref() {
+            "list" => builder = builder.completion_type(CompletionType::List),
+            "circular" => {
+                builder = builder.completion_type(CompletionType::Circular)
+            }
+            _ => println!("--> unknown completion type [ {} ] of prompt", kind),
+        }
+    }
+
+    if let Some(size) = settings.history_size {
+        builder = builder.max_history_size(size);
+    }
+
+    if let Some(ignore) = settings.history_ignore_dups {
+        builder = builder.history_ignore_dups(ignore);
+    }
+
+    return builder;
+}
+
+// Return the key and the editor command of a binding
+fn key_binding(binding: &config::KeyBinding) -> Result<(KeyPress, Cmd), String> {
+    let key = match parse_key(&binding.key) {
+        Some(key) => key,
+        None => return Err(format!("unknown key [ {} ]", binding.key)),
+    };
+
+    match (&binding.action, &binding.command) {
+        (Some(action), None) => match parse_action(action) {
+            Some(cmd) => return Ok((key, cmd)),
+            None => return Err(format!("unknown action [ {} ]", action)),
+        },
+        (None, Some(_)) => return Ok((key, Cmd::Interrupt)),
+        _ => {
+            return Err(format!(
+                "key [ {} ] must be bound to either an action or a command",
+                binding.key
+            ))
+        }
+    }
+}
+
+// Parse key names such as `F5`, `Ctrl-R`, `Alt-f`, `Shift-Up` or `x`
+fn parse_key(text: &str) -> Option<KeyPress> {
+    let (modifier, name) = match text.find('-') {
+        Some(i) if i > 0 && i + 1 < text.len() => (&text[..i], &text[i + 1..]),
+        _ => ("", text),
+    };
+    let single = single_char(name);
+    let modifier = modifier.to_lowercase();
+    let name = name.to_lowercase();
+
+    match (modifier.as_ref(), name.as_ref()) {
+        ("ctrl", "up") | ("c", "up") => return Some(KeyPress::ControlUp),
+        ("ctrl", "down") | ("c", "down") => return Some(KeyPress::ControlDown),
+        ("ctrl", "left") | ("c", "left") => return Some(KeyPress::ControlLeft),
+        ("ctrl", "right") | ("c", "right") => {
+            return Some(KeyPress::ControlRight)
+        }
+        ("ctrl", _) | ("c", _) => {
+            return single.map(|c| KeyPress::Ctrl(c.to_ascii_uppercase()))
+        }
+        ("alt", _) | ("meta", _) | ("m", _) => return single.map(KeyPress::Meta),
+        ("shift", "up") => return Some(KeyPress::ShiftUp),
+        ("shift", "down") => return Some(KeyPress::ShiftDown),
+        ("shift", "left") => return Some(KeyPress::ShiftLeft),
+        ("shift", "right") => return Some(KeyPress::ShiftRight),
+        ("shift", "tab") | ("", "backtab") => return Some(KeyPress::BackTab),
+        ("", "up") => return Some(KeyPress::Up),
+        ("", "down") => return Some(KeyPress::Down),
+        ("", "left") => return Some(KeyPress::Left),
+        ("", "right") => return Some(KeyPress::Right),
+        ("", "home") => return Some(KeyPress::Home),
+        ("", "end") => return Some(KeyPress::End),
+        ("", "pageup") => return Some(KeyPress::PageUp),
+        ("", "pagedown") => return Some(KeyPress::PageDown),
+        ("", "tab") => return Some(KeyPress::Tab),
+        ("", "esc") => return Some(KeyPress::Esc),
+        ("", "enter") => return Some(KeyPress::Enter),
+        ("", "backspace") => return Some(KeyPress::Backspace),
+        ("", "delete") => return Some(KeyPress::Delete),
+        ("", "insert") => return Some(KeyPress::Insert),
+        ("", _) if name.starts_with('f') && name.len() > 1 => {
+            match name[1..].parse::<u8>() {
+                Ok(n) if n >= 1 && n <= 12 => return Some(KeyPress::F(n)),
+                _ => return single.map(KeyPress::Char),
+            }
+        }
+        ("", _) => return single.map(KeyPress::Char),
+        _ => return None,
+    }
+}
+
+fn single_char(text: &str) -> Option<char> {
+    let mut chars = text.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => return Some(c),
+        _ => return None,
+    }
+}
+
+// Parse readline command names such as `history-search-backward`
+fn parse_action(name: &str) -> Option<Cmd> {
+    let cmd = match name {
+        "abort" => Cmd::Abort,
+        "accept-line" => Cmd::AcceptLine,
+        "beginning-of-history" => Cmd::BeginningOfHistory,
+        "end-of-history" => Cmd::EndOfHistory,
+        "capitalize-word" => Cmd::CapitalizeWord,
+        "downcase-word" => Cmd::DowncaseWord,
+        "upcase-word" => Cmd::UpcaseWord,
+        "clear-screen" => Cmd::ClearScreen,
+        "complete" => Cmd::Complete,
+        "complete-hint" => Cmd::CompleteHint,
+        "history-search-backward" => Cmd::HistorySearchBackward,
+        "history-search-forward" => Cmd::HistorySearchForward,
+        "reverse-search-history" => Cmd::ReverseSearchHistory,
+        "forward-search-history" => Cmd::ForwardSearchHistory,
+        "previous-history" => Cmd::PreviousHistory,
+        "next-history" => Cmd::NextHistory,
+        "transpose-chars" => Cmd::TransposeChars,
+        "undo" => Cmd::Undo(1),
+        "yank" => Cmd::Yank(1, Anchor::Before),
+        "beginning-of-line" => Cmd::Move(Movement::BeginningOfLine),
+        "end-of-line" => Cmd::Move(Movement::EndOfLine),
+        "backward-char" => Cmd::Move(Movement::BackwardChar(1)),
+        "forward-char" => Cmd::Move(Movement::ForwardChar(1)),
+        "backward-word" => Cmd::Move(Movement::BackwardWord(1, Word::Emacs)),
+        "forward-word" => {
+            Cmd::Move(Movement::ForwardWord(1, At::AfterEnd, Word::Emacs))
+        }
+        "kill-line" => Cmd::Kill(Movement::EndOfLine),
+        "backward-kill-line" => Cmd::Kill(Movement::BeginningOfLine),
+        "kill-whole-line" => Cmd::Kill(Movement::WholeLine),
+        _ => return None,
+    };
+    return Some(cmd);
+}
+
+// Value of a git prompt segment, empty if there is nothing to show
+fn git_segment(name: &str, status: Option<&GitStatus>) -> String {
+    let status = match status {
+        Some(status) => status,
+        None => return String::new(),
+    };
 
-        let dir = format!("{}{} ~ {}", style::Bold, color::Fg(Cyan), dir);
-        let prompt = format!("{} ➜ {}", color::Fg(Yellow), style::Reset);
+    let count = |symbol: &str, n: usize| {
+        if n == 0 {
+            return String::new();
+        }
+        return format!("{}{}", symbol, n);
+    };
 
-        if branch.is_empty() {
-            return format!("{}{}", dir, prompt);
+    match name {
+        "branch" if status.detached => {
+            return format!("(detached {})", status.branch);
         }
+        "branch" => return status.branch.to_owned(),
+        "dirty" if status.is_dirty() => return String::from("*"),
+        "staged" => return count("+", status.staged),
+        "unstaged" => return count("!", status.unstaged),
+        "untracked" => return count("?", status.untracked),
+        "ahead" => return count("↑", status.ahead),
+        "behind" => return count("↓", status.behind),
+        _ => return String::new(),
+    }
+}
 
-        let branch = format!("{} {}", color::Fg(Green), branch);
-        return format!("{}{}{}", dir, branch, prompt);
+// Value of a prompt segment that doesn't depend on current directory, or
+// None if the segment is unknown
+fn segment(
+    name: &str,
+    cfg: &config::Config,
+    last: &CommandResult,
+) -> Option<String> {
+    match name {
+        "project" => return Some(cfg.project.to_owned()),
+        "using_groups" => match &cfg.using {
+            Some(using) => return Some(using.join(",")),
+            None => return Some(String::new()),
+        },
+        "machine" => match &cfg.machine {
+            Some(machine) => return Some(machine.name.to_owned()),
+            None => return Some(String::new()),
+        },
+        "last_status" => {
+            if last.success() {
+                return Some(String::new());
+            }
+            return Some(last.exit_code().to_string());
+        }
+        "duration" => {
+            let seconds = last.duration.as_secs_f64();
+            if seconds < MIN_DURATION_SECONDS {
+                return Some(String::new());
+            }
+            return Some(format!("{:.1}s", seconds));
+        }
+        _ => return None,
     }
 }
 
+// Replace each {segment} of the template by its value and each {style} by
+// its terminal escape code. One space following an empty segment is dropped
+// so that missing segments don't leave gaps. Unknown names are kept as is
+fn render_template<F>(template: &str, value: F) -> String
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(open) = rest.find('{') {
+        result.push_str(&rest[..open]);
+        rest = &rest[open..];
+
+        let close = match rest.find('}') {
+            Some(close) => close,
+            None => break,
+        };
+        let name = &rest[1..close];
+        rest = &rest[close + 1..];
+
+        if let Some(code) = style_code(name) {
+            result.push_str(&code);
+            continue;
+        }
+
+        match value(name) {
+            Some(text) => {
+                if text.is_empty() && rest.starts_with(' ') {
+                    rest = &rest[1..];
+                }
+                result.push_str(&text);
+            }
+            None => result.push_str(&format!("{{{}}}", name)),
+        }
+    }
+
+    result.push_str(rest);
+    return result;
+}
+
+fn style_code(name: &str) -> Option<String> {
+    let code = match name {
+        "reset" => style::Reset.to_string(),
+        "bold" => style::Bold.to_string(),
+        "red" => color::Fg(color::Red).to_string(),
+        "green" => color::Fg(color::Green).to_string(),
+        "yellow" => color::Fg(color::Yellow).to_string(),
+        "blue" => color::Fg(color::Blue).to_string(),
+        "magenta" => color::Fg(color::Magenta).to_string(),
+        "cyan" => color::Fg(color::Cyan).to_string(),
+        "white" => color::Fg(color::White).to_string(),
+        "grey" => color::Fg(color::LightBlack).to_string(),
+        _ => return None,
+    };
+    return Some(code);
+}
+
+// Names that can be completed, collected from the project config and the
+// command registry when the prompt is created
+pub struct Vocabulary {
+    pub commands: Vec<String>,
+    pub services: Vec<String>,
+    pub dependencies: Vec<String>,
+    pub repositories: Vec<String>,
+    pub groups: Vec<String>,
+}
+
+impl Vocabulary {
+    pub fn new(cfg: &config::Config) -> Self {
+        let mut commands: Vec<String> = Vec::new();
+        for descriptor in COMMANDS {
+            commands.push(String::from(descriptor.name));
+            for alias in descriptor.aliases {
+                commands.push(String::from(*alias));
+            }
+        }
+        if let Some(customs) = &cfg.commands {
+            commands.extend(customs.iter().map(|c| c.name.to_owned()));
+        }
+        if let Some(shortcuts) = &cfg.shortcuts {
+            for shortcut in shortcuts {
+                commands.extend(shortcut.prefixes.iter().cloned());
+            }
+        }
+
+        let mut services: Vec<String> = Vec::new();
+        if let Some(svcs) = &cfg.services {
+            services.extend(svcs.iter().map(|s| s.name.to_owned()));
+        }
+
+        let mut dependencies: Vec<String> = Vec::new();
+        if let Some(deps) = &cfg.dependencies {
+            dependencies.extend(deps.iter().map(|d| d.name.to_owned()));
+        }
+
+        let mut repositories: Vec<String> = Vec::new();
+        if let Some(repos) = &cfg.repositories {
+            repositories.extend(repos.iter().map(|r| r.name.to_owned()));
+        }
+
+        let mut groups: Vec<String> = Vec::new();
+        if let Some(grps) = &cfg.groups {
+            groups.extend(grps.iter().map(|g| g.name.to_owned()));
+        }
+
+        return Self {
+            commands,
+            services,
+            dependencies,
+            repositories,
+            groups,
+        };
+    }
+
+    fn names(&self, completion: Completion) -> &[String] {
+        match completion {
+            Completion::Commands => return &self.commands,
+            Completion::Services => return &self.services,
+            Completion::Dependencies => return &self.dependencies,
+            Completion::Repositories => return &self.repositories,
+            Completion::Groups => return &self.groups,
+        }
+    }
+
+    // Return names that can complete the word at the given position of the
+    // line, or None if the word should be completed as a file name
+    pub fn complete(&self, line: &str, pos: usize) -> Option<Vec<String>> {
+        let line = &line[..pos];
+        let start = line.rfind(' ').map_or(0, |i| i + 1);
+        let word = &line[start..];
+        let tokens: Vec<&str> = line[..start].split_whitespace().collect();
+
+        let names: Vec<&String> = match tokens.split_first() {
+            None => self.commands.iter().collect(),
+            Some((program, args)) => {
+                let descriptor = registry::search(COMMANDS, program)?;
+                if args.is_empty() && !descriptor.subcommands.is_empty() {
+                    let subcommands = descriptor.subcommands.iter();
+                    return Some(filter_names(subcommands, word));
+                }
+
+                if args.len() < descriptor.free_args {
+                    return Some(Vec::new());
+                }
+                if descriptor.completions.is_empty() {
+                    return None;
+                }
+
+                descriptor
+                    .completions
+                    .iter()
+                    .flat_map(|completion| self.names(*completion))
+                    .collect()
+            }
+        };
+
+        return Some(filter_names(names.into_iter(), word));
+    }
+
+    // Color the line: known commands in green, known names of services,
+    // repositories, groups or subcommands in yellow and unknown ones in red.
+    // The word being typed at the cursor is not marked as unknown while it
+    // can still become a known name
+    pub fn highlight(&self, line: &str, pos: usize) -> String {
+        let mut words: Vec<String> = Vec::new();
+        let mut program: Option<&str> = None;
+        let mut index = 0;
+        let mut end = 0;
+
+        for word in line.split(' ') {
+            end += word.len();
+
+            let colored = if word.is_empty() {
+                String::new()
+            } else if let Some(program) = program {
+                index += 1;
+                self.highlight_argument(program, index - 1, word, end == pos)
+            } else {
+                program = Some(word);
+                if self.commands.iter().any(|name| name == word) {
+                    decr::green(word)
+                } else {
+                    String::from(word)
+                }
+            };
+
+            words.push(colored);
+            end += 1;
+        }
+
+        return words.join(" ");
+    }
+
+    fn highlight_argument(
+        &self,
+        program: &str,
+        index: usize,
+        word: &str,
+        typing: bool,
+    ) -> String {
+        match self.argument_names(program, index) {
+            Some(_) if word.starts_with("--") => return String::from(word),
+            Some(names) => {
+                if names.contains(&word) {
+                    return decr::yellow(word);
+                }
+                if typing && names.iter().any(|name| name.starts_with(word)) {
+                    return String::from(word);
+                }
+                return decr::red(word);
+            }
+            None => return String::from(word),
+        }
+    }
+
+    // Names accepted by the argument at index of a program, or None if the
+    // argument can be anything
+    fn argument_names(&self, program: &str, index: usize) -> Option<Vec<&str>> {
+        let descriptor = registry::search(COMMANDS, program)?;
+
+        if index == 0 && !descriptor.subcommands.is_empty() {
+            return Some(descriptor.subcommands.to_vec());
+        }
+
+        if index < descriptor.free_args || descriptor.completions.is_empty() {
+            return None;
+        }
+
+        let names = descriptor
+            .completions
+            .iter()
+            .flat_map(|completion| self.names(*completion))
+            .map(String::as_ref)
+            .collect();
+        return Some(names);
+    }
+}
+
+// Suggest the rest of the most recent history entry that starts with the
+// line, only when the cursor is at the end of the line
+fn history_hint(history: &[String], line: &str, pos: usize) -> Option<String> {
+    if line.is_empty() || pos < line.len() {
+        return None;
+    }
+
+    for entry in history.iter().rev() {
+        if entry.len() > line.len() && entry.starts_with(line) {
+            return Some(String::from(&entry[line.len()..]));
+        }
+    }
+
+    return None;
+}
+
+// Return sorted distinct names starting with the prefix
+fn filter_names<I, S>(names: I, prefix: &str) -> Vec<String>
+where
+    I: Iterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut result: Vec<String> = names
+        .filter(|name| name.as_ref().starts_with(prefix))
+        .map(|name| String::from(name.as_ref()))
+        .collect();
+    result.sort();
+    result.dedup();
+    return result;
+}
+
 struct PromptHelper {
     completer: FilenameCompleter,
+    vocabulary: Vocabulary,
+    history: Vec<String>,
 }
 
 impl Completer for PromptHelper {
     type Candidate = Pair;
 
     fn complete(&self, line: &str, pos: usize) -> Result<(usize, Vec<Pair>), ReadlineError> {
-        self.completer.complete(line, pos)
+        match self.vocabulary.complete(line, pos) {
+            Some(names) => {
+                let start = line[..pos].rfind(' ').map_or(0, |i| i + 1);
+                let pairs = names
+                    .into_iter()
+                    .map(|name| Pair {
+                        replacement: format!("{} ", name),
+                        display: name,
+                    })
+                    .collect();
+                return Ok((start, pairs));
+            }
+            None => return self.completer.complete(line, pos),
+        }
     }
 }
 
 impl Hinter for PromptHelper {
-    fn hint(&self, _line: &str, _pos: usize) -> Option<String> {
-        None
+    fn hint(&self, line: &str, pos: usize) -> Option<String> {
+        return history_hint(&self.history, line, pos);
     }
 }
 
 impl Helper for PromptHelper {}
 
-impl Highlighter for PromptHelper {}
+impl Highlighter for PromptHelper {
+    fn highlight<'l>(&self, line: &'l str, pos: usize) -> Cow<'l, str> {
+        return Owned(self.vocabulary.highlight(line, pos));
+    }
+
+    fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+        return Owned(decr::grey(hint));
+    }
+
+    fn highlight_char(&self, _line: &str, _pos: usize) -> bool {
+        return true;
+    }
+}
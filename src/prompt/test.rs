@@ -1 +1,391 @@
+use super::*;
+use crate::cmd::{Command, ErrorKind};
+use rustyline::config::HistoryDuplicates;
+use std::env;
+use std::fs;
+use std::time::Duration;
 
+const CONFIG_FILE: &str = "etc/sample.config.toml";
+
+fn sample_vocabulary() -> Vocabulary {
+    let cfg = config::Config::load(CONFIG_FILE).expect("cannot load config");
+    return Vocabulary::new(&cfg);
+}
+
+fn complete(line: &str) -> Option<Vec<String>> {
+    let vocabulary = sample_vocabulary();
+    return vocabulary.complete(line, line.len());
+}
+
+fn names(names: &[&str]) -> Option<Vec<String>> {
+    return Some(names.iter().map(|s| String::from(*s)).collect());
+}
+
+#[test]
+fn test_new_vocabulary() {
+    let vocabulary = sample_vocabulary();
+
+    assert!(vocabulary.commands.contains(&String::from("quit")));
+    assert!(vocabulary.commands.contains(&String::from("exit")));
+    assert!(vocabulary.commands.contains(&String::from("migrate")));
+    assert!(vocabulary.commands.contains(&String::from("db")));
+    assert_eq!(vocabulary.services, vec!["camellia", "lotus"]);
+    assert_eq!(vocabulary.dependencies, vec!["postgres", "redis"]);
+    assert_eq!(vocabulary.repositories, vec!["flowers", "turtle"]);
+    assert_eq!(vocabulary.groups, vec!["all", "svc", "dep"]);
+}
+
+#[test]
+fn test_complete_command() {
+    assert_eq!(complete("st"), names(&["start", "status", "stop"]));
+    assert_eq!(complete("mi"), names(&["migrate"]));
+    let expect = names(&["db", "dk", "dkcp", "dkmc", "dns", "dryrun"]);
+    assert_eq!(complete("d"), expect);
+    assert_eq!(complete("unknown"), names(&[]));
+}
+
+#[test]
+fn test_complete_arguments() {
+    let expect = names(&["camellia", "flowers", "lotus", "turtle"]);
+    assert_eq!(complete("goto "), expect);
+    assert_eq!(complete("logs l"), names(&["lotus"]));
+    assert_eq!(complete("bash p"), names(&["postgres"]));
+    assert_eq!(complete("use "), names(&["all", "dep", "svc"]));
+    let expect =
+        names(&["all", "camellia", "dep", "flowers", "lotus", "svc", "turtle"]);
+    assert_eq!(complete("build lotus "), expect);
+    assert_eq!(complete("help dkm"), names(&["dkmc"]));
+}
+
+#[test]
+fn test_complete_free_arguments() {
+    assert_eq!(complete("checkout fe"), Some(Vec::new()));
+    assert_eq!(complete("branch feature tu"), names(&["turtle"]));
+}
+
+#[test]
+fn test_complete_subcommands() {
+    assert_eq!(complete("dkmc st"), names(&["start", "status", "stop"]));
+    assert_eq!(complete("dns r"), names(&["resolve", "restart"]));
+    assert_eq!(complete("dns update "), None);
+}
+
+#[test]
+fn test_complete_file_names() {
+    assert_eq!(complete("cd sr"), None);
+    assert_eq!(complete("ls -la sr"), None);
+    assert_eq!(complete("migrate d"), None);
+}
+
+#[test]
+fn test_complete_middle_of_line() {
+    let vocabulary = sample_vocabulary();
+    assert_eq!(vocabulary.complete("logs ca lotus", 7), names(&["camellia"]));
+}
+
+#[test]
+fn test_highlight() {
+    let vocabulary = sample_vocabulary();
+
+    let line = "restart  lotus lotsu";
+    let expect = format!(
+        "{}  {} {}",
+        decr::green("restart"),
+        decr::yellow("lotus"),
+        decr::red("lotsu")
+    );
+    assert_eq!(vocabulary.highlight(line, 0), expect);
+
+    let line = "ls -la src";
+    assert_eq!(vocabulary.highlight(line, line.len()), line);
+
+    let line = "dns foo ";
+    let expect = format!("{} {} ", decr::green("dns"), decr::red("foo"));
+    assert_eq!(vocabulary.highlight(line, line.len()), expect);
+
+    let line = "dkcp exec lotus";
+    let expect = format!("{} exec lotus", decr::green("dkcp"));
+    assert_eq!(vocabulary.highlight(line, line.len()), expect);
+
+    let line = "checkout feature --stash svc";
+    let expect = format!(
+        "{} feature --stash {}",
+        decr::green("checkout"),
+        decr::yellow("svc")
+    );
+    assert_eq!(vocabulary.highlight(line, 0), expect);
+}
+
+#[test]
+fn test_highlight_typing() {
+    let vocabulary = sample_vocabulary();
+
+    let line = "logs lo";
+    let expect = format!("{} lo", decr::green("logs"));
+    assert_eq!(vocabulary.highlight(line, line.len()), expect);
+
+    let expect = format!("{} {}", decr::green("logs"), decr::red("lo"));
+    assert_eq!(vocabulary.highlight(line, 2), expect);
+}
+
+#[test]
+fn test_history_hint() {
+    let history = vec![
+        String::from("build lotus"),
+        String::from("restart camellia"),
+        String::from("build camellia"),
+    ];
+
+    let hint = history_hint(&history, "build", 5);
+    assert_eq!(hint, Some(String::from(" camellia")));
+
+    let hint = history_hint(&history, "re", 2);
+    assert_eq!(hint, Some(String::from("start camellia")));
+
+    assert_eq!(history_hint(&history, "re", 1), None);
+    assert_eq!(history_hint(&history, "", 0), None);
+    assert_eq!(history_hint(&history, "build camellia", 14), None);
+    assert_eq!(history_hint(&history, "stop", 4), None);
+}
+
+#[test]
+fn test_history_hint_size() {
+    let mut cfg = config::Config::new("forest");
+    cfg.prompt = Some(config::Prompt {
+        template: None,
+        dir_max_length: None,
+        edit_mode: None,
+        completion_type: None,
+        history_size: Some(2),
+        history_ignore_dups: None,
+        bindings: None,
+    });
+
+    let history_file = env::temp_dir().join("turtle-history-hint.txt");
+    let history_file = history_file.to_str().unwrap();
+    fs::write(history_file, "build lotus\nbuild camellia\ntest\n").unwrap();
+
+    let mut prompt = Prompt::new(&cfg);
+    prompt.load_history(history_file);
+    fs::remove_file(history_file).expect("cannot remove history file");
+
+    let helper = prompt.editor.helper().unwrap();
+    assert_eq!(helper.history, vec!["build camellia", "test"]);
+    assert_eq!(helper.hint("build", 5), Some(String::from(" camellia")));
+}
+
+#[test]
+fn test_render_template() {
+    let value = |name: &str| match name {
+        "project" => Some(String::from("forest")),
+        "branch" => Some(String::new()),
+        _ => None,
+    };
+
+    let output = render_template("[{project}] {branch} ➜ ", value);
+    assert_eq!(output, "[forest] ➜ ");
+
+    let output = render_template("{red}{project}{reset}", value);
+    let expect = format!("{}forest{}", color::Fg(color::Red), style::Reset);
+    assert_eq!(output, expect);
+
+    let output = render_template("{unknown} {project", value);
+    assert_eq!(output, "{unknown} {project");
+}
+
+#[test]
+fn test_segment() {
+    let cfg = config::Config::load(CONFIG_FILE).expect("cannot load config");
+    let mut last = CommandResult::ok("");
+
+    assert_eq!(segment("project", &cfg, &last), Some(String::from("sample")));
+    assert_eq!(segment("using_groups", &cfg, &last), Some(String::from("all")));
+    assert_eq!(segment("machine", &cfg, &last), Some(String::from("turtle")));
+    assert_eq!(segment("last_status", &cfg, &last), Some(String::new()));
+    assert_eq!(segment("duration", &cfg, &last), Some(String::new()));
+    assert_eq!(segment("unknown", &cfg, &last), None);
+
+    last = CommandResult::fail(ErrorKind::Exit, "");
+    last.code = Some(2);
+    last.duration = Duration::from_millis(2500);
+    assert_eq!(segment("last_status", &cfg, &last), Some(String::from("2")));
+    assert_eq!(segment("duration", &cfg, &last), Some(String::from("2.5s")));
+
+    let cfg = config::Config::new("forest");
+    assert_eq!(segment("using_groups", &cfg, &last), Some(String::new()));
+    assert_eq!(segment("machine", &cfg, &last), Some(String::new()));
+}
+
+#[test]
+fn test_git_segment() {
+    let mut status = GitStatus {
+        branch: String::from("feature"),
+        upstream: String::new(),
+        detached: false,
+        staged: 1,
+        unstaged: 0,
+        untracked: 3,
+        ahead: 2,
+        behind: 0,
+    };
+
+    assert_eq!(git_segment("branch", Some(&status)), "feature");
+    assert_eq!(git_segment("dirty", Some(&status)), "*");
+    assert_eq!(git_segment("staged", Some(&status)), "+1");
+    assert_eq!(git_segment("unstaged", Some(&status)), "");
+    assert_eq!(git_segment("untracked", Some(&status)), "?3");
+    assert_eq!(git_segment("ahead", Some(&status)), "↑2");
+    assert_eq!(git_segment("behind", Some(&status)), "");
+    assert_eq!(git_segment("branch", None), "");
+
+    status.detached = true;
+    status.branch = String::from("4f2c1d9");
+    let branch = git_segment("branch", Some(&status));
+    assert_eq!(branch, "(detached 4f2c1d9)");
+}
+
+#[test]
+fn test_parse_key() {
+    assert_eq!(parse_key("F5"), Some(KeyPress::F(5)));
+    assert_eq!(parse_key("f12"), Some(KeyPress::F(12)));
+    assert_eq!(parse_key("Ctrl-r"), Some(KeyPress::Ctrl('R')));
+    assert_eq!(parse_key("C-Up"), Some(KeyPress::ControlUp));
+    assert_eq!(parse_key("Alt-f"), Some(KeyPress::Meta('f')));
+    assert_eq!(parse_key("M-b"), Some(KeyPress::Meta('b')));
+    assert_eq!(parse_key("Shift-Tab"), Some(KeyPress::BackTab));
+    assert_eq!(parse_key("PageDown"), Some(KeyPress::PageDown));
+    assert_eq!(parse_key("x"), Some(KeyPress::Char('x')));
+    assert_eq!(parse_key("-"), Some(KeyPress::Char('-')));
+    assert_eq!(parse_key("f"), Some(KeyPress::Char('f')));
+    assert_eq!(parse_key("F13"), None);
+    assert_eq!(parse_key("Hyper-x"), None);
+    assert_eq!(parse_key("Ctrl-xy"), None);
+}
+
+#[test]
+fn test_parse_action() {
+    let cmd = parse_action("history-search-backward");
+    assert_eq!(cmd, Some(Cmd::HistorySearchBackward));
+
+    let cmd = parse_action("beginning-of-line");
+    assert_eq!(cmd, Some(Cmd::Move(Movement::BeginningOfLine)));
+
+    assert_eq!(parse_action("unknown"), None);
+}
+
+#[test]
+fn test_key_binding() {
+    let mut binding = config::KeyBinding {
+        key: String::from("F5"),
+        action: Some(String::from("clear-screen")),
+        command: None,
+    };
+    assert_eq!(key_binding(&binding), Ok((KeyPress::F(5), Cmd::ClearScreen)));
+
+    binding.action = Some(String::from("fly"));
+    let message = "unknown action [ fly ]";
+    assert_eq!(key_binding(&binding), Err(String::from(message)));
+
+    binding.command = Some(String::from("status"));
+    let message = "key [ F5 ] must be bound to either an action or a command";
+    assert_eq!(key_binding(&binding), Err(String::from(message)));
+
+    binding.action = None;
+    assert_eq!(key_binding(&binding), Ok((KeyPress::F(5), Cmd::Interrupt)));
+
+    binding.command = None;
+    assert_eq!(key_binding(&binding), Err(String::from(message)));
+
+    binding.key = String::from("Hyper-x");
+    let message = "unknown key [ Hyper-x ]";
+    assert_eq!(key_binding(&binding), Err(String::from(message)));
+}
+
+#[test]
+fn test_command_binding() {
+    let cfg = config::Config::load(CONFIG_FILE).expect("cannot load config");
+    let mut prompt = Prompt::new(&cfg);
+
+    let line = prompt.accepted_line(Err(ReadlineError::Interrupted));
+    assert_eq!(line, "status");
+    let line = prompt.accepted_line(Ok(String::from("ps")));
+    assert_eq!(line, "ps");
+    assert_eq!(prompt.accepted_line(Err(ReadlineError::Eof)), "");
+
+    let cfg = config::Config::new("forest");
+    let mut prompt = Prompt::new(&cfg);
+    let line = prompt.accepted_line(Err(ReadlineError::Interrupted));
+    assert_eq!(line, "");
+}
+
+#[test]
+fn test_configure() {
+    let cfg = config::Config::load(CONFIG_FILE).expect("cannot load config");
+    let settings = cfg.prompt.expect("prompt config is missing");
+
+    let config = configure(Config::builder(), &settings).build();
+    assert_eq!(config.edit_mode(), EditMode::Vi);
+    assert_eq!(config.completion_type(), CompletionType::List);
+    assert_eq!(config.max_history_size(), 1000);
+    let duplicates = HistoryDuplicates::IgnoreConsecutive;
+    assert_eq!(config.history_duplicates(), duplicates);
+
+    let settings = config::Prompt {
+        template: None,
+        dir_max_length: None,
+        edit_mode: Some(String::from("unknown")),
+        completion_type: Some(String::from("circular")),
+        history_size: None,
+        history_ignore_dups: Some(false),
+        bindings: None,
+    };
+    let config = configure(Config::builder(), &settings).build();
+    assert_eq!(config.edit_mode(), EditMode::Emacs);
+    assert_eq!(config.completion_type(), CompletionType::Circular);
+    assert_eq!(config.history_duplicates(), HistoryDuplicates::AlwaysAdd);
+}
+
+// Remove a test directory when it goes out of scope, even if the test fails
+struct TestDir(String);
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[test]
+fn test_git_probe() {
+    let test_dir = env::temp_dir().join("turtle-git-probe");
+    let test_dir = TestDir(test_dir.to_string_lossy().into_owned());
+    let dir = &test_dir.0;
+    let _ = fs::remove_dir_all(dir);
+    fs::create_dir_all(dir).expect("cannot create test directory");
+
+    let git = |args: &str| {
+        let raw = format!("git -C {} {}", dir, args);
+        assert!(shell::run_command(&Command::basic_hide(&raw)).success());
+    };
+    git("init -q");
+    git("checkout -q -b lotus");
+
+    let mut probe = GitProbe::new();
+    let status = probe.status(dir).expect("cannot probe git status");
+    assert_eq!(status.branch, "lotus");
+    assert!(!status.detached);
+    assert!(!status.is_dirty());
+
+    // New files don't touch the git directory, the cached status is only
+    // refreshed after it is cleared
+    fs::write(format!("{}/README.md", dir), "lotus").unwrap();
+    assert_eq!(probe.status(dir), Some(status));
+
+    probe.clear();
+    let status = probe.status(dir).expect("cannot probe git status");
+    assert_eq!(status.untracked, 1);
+
+    git("add README.md");
+    let status = probe.status(dir).expect("cannot probe git status");
+    assert_eq!(status.staged, 1);
+    assert!(status.is_dirty());
+}
@@ -0,0 +1,193 @@
+#[cfg(test)]
+mod test;
+
+use std::cmp;
+
+use super::config::CustomCommand;
+use super::gen::Generator;
+use super::instr::Instruction;
+
+pub type Handler = fn(&mut Generator, &str, &[&str]) -> Instruction;
+
+pub struct Usage {
+    pub args: &'static str,
+    pub summary: &'static str,
+}
+
+// Kinds of names that arguments of a command can be completed with
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Completion {
+    Commands,
+    Services,
+    Dependencies,
+    Repositories,
+    Groups,
+}
+
+pub struct Descriptor {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    pub subcommands: &'static [&'static str],
+    // Number of leading arguments that can be anything, such as a branch name
+    pub free_args: usize,
+    pub completions: &'static [Completion],
+    pub summary: &'static str,
+    pub usages: &'static [Usage],
+    pub handler: Handler,
+}
+
+impl Descriptor {
+    pub fn matches(&self, name: &str) -> bool {
+        return self.name == name || self.aliases.contains(&name);
+    }
+
+    // Return an error message with usage if args don't start with one of the
+    // supported subcommands, or None if args are acceptable
+    pub fn check_subcommand(&self, args: &[&str]) -> Option<String> {
+        if self.subcommands.is_empty() {
+            return None;
+        }
+
+        // Commands having a usage without arguments can omit the subcommand
+        let optional = self.usages.iter().any(|usage| usage.args.is_empty());
+
+        let message = match args.first() {
+            Some(sub) if self.subcommands.contains(sub) => return None,
+            None if optional => return None,
+            Some(sub) => format!(
+                "--> unknown subcommand [ {} ] of [ {} ]",
+                sub, self.name
+            ),
+            None => format!("--> missing subcommand of [ {} ]", self.name),
+        };
+
+        return Some(format!("{}\n{}", message, usage(self)));
+    }
+}
+
+pub fn search<'a>(
+    commands: &'a [Descriptor],
+    name: &str,
+) -> Option<&'a Descriptor> {
+    for descriptor in commands {
+        if descriptor.matches(name) {
+            return Some(descriptor);
+        }
+    }
+    return None;
+}
+
+// Return usage lines of a command, one for each supported form
+pub fn usage(descriptor: &Descriptor) -> String {
+    let rows: Vec<(String, &str)> = descriptor
+        .usages
+        .iter()
+        .map(|u| (usage_line(descriptor.name, u.args), u.summary))
+        .collect();
+
+    let mut lines = vec![format!("--> usage of [ {} ]:", descriptor.name)];
+    lines.extend(align_rows(&rows));
+
+    if !descriptor.aliases.is_empty() {
+        let aliases = descriptor.aliases.join(", ");
+        lines.push(format!("--> aliases: {}", aliases));
+    }
+
+    return lines.join("\n");
+}
+
+pub fn custom_usage(custom: &CustomCommand) -> String {
+    let mut line = custom.name.clone();
+    if let Some(args) = &custom.args {
+        for arg in args {
+            line.push_str(&format!(" {{{}}}", arg));
+        }
+    }
+
+    let summary = match &custom.description {
+        Some(description) => description.as_ref(),
+        None => "",
+    };
+
+    let mut lines = vec![format!("--> usage of [ {} ]:", custom.name)];
+    lines.extend(align_rows(&[(line, summary)]));
+    return lines.join("\n");
+}
+
+// Return a summary of all built-in and custom commands
+pub fn help(commands: &[Descriptor], customs: &[CustomCommand]) -> String {
+    let rows: Vec<(String, &str)> = commands
+        .iter()
+        .map(|d| {
+            let mut names = vec![d.name];
+            names.extend(d.aliases);
+            (names.join(", "), d.summary)
+        })
+        .collect();
+
+    let mut lines = vec![String::from("--> supported commands:")];
+    lines.extend(align_rows(&rows));
+
+    if !customs.is_empty() {
+        let rows: Vec<(String, &str)> = customs
+            .iter()
+            .map(|c| {
+                let summary = match &c.description {
+                    Some(description) => description.as_ref(),
+                    None => "",
+                };
+                (c.name.clone(), summary)
+            })
+            .collect();
+
+        lines.push(String::from("--> custom commands:"));
+        lines.extend(align_rows(&rows));
+    }
+
+    lines.push(String::from("--> type help {command} for more details"));
+    return lines.join("\n");
+}
+
+// Return the markdown table of all commands used in README.md
+pub fn markdown_table(commands: &[Descriptor]) -> String {
+    let mut lines = vec![
+        String::from("**Command** | **Description**"),
+        String::from("--------|-------------"),
+    ];
+
+    for descriptor in commands {
+        let mut names = vec![descriptor.name];
+        names.extend(descriptor.aliases);
+
+        for name in names {
+            for u in descriptor.usages {
+                let line = usage_line(name, u.args);
+                lines.push(format!("`{}`|{}", line, u.summary));
+            }
+        }
+    }
+
+    return lines.join("\n");
+}
+
+fn usage_line(name: &str, args: &str) -> String {
+    if args.is_empty() {
+        return String::from(name);
+    }
+    return format!("{} {}", name, args);
+}
+
+fn align_rows(rows: &[(String, &str)]) -> Vec<String> {
+    let mut width = 0;
+    for (left, _) in rows {
+        width = cmp::max(width, left.len());
+    }
+
+    return rows
+        .iter()
+        .map(|(left, right)| {
+            let line = format!("    {:w$}    {}", left, right, w = width);
+            String::from(line.trim_end())
+        })
+        .collect();
+}
@@ -0,0 +1,98 @@
+use super::*;
+use crate::gen::COMMANDS;
+
+use std::fs;
+
+fn sample_custom() -> CustomCommand {
+    return CustomCommand {
+        name: String::from("migrate"),
+        description: Some(String::from("Run database migrations.")),
+        args: Some(vec![String::from("database")]),
+        service: None,
+        dir: None,
+        timeout: None,
+        steps: vec![String::from("diesel migration run")],
+    };
+}
+
+#[test]
+fn test_search() {
+    let found = search(COMMANDS, "exit");
+    assert!(found.is_some());
+    assert_eq!(found.unwrap().name, "quit");
+
+    let found = search(COMMANDS, "unknown");
+    assert!(found.is_none());
+}
+
+#[test]
+fn test_check_subcommand() {
+    let descriptor = search(COMMANDS, "dns").unwrap();
+    assert!(descriptor.check_subcommand(&["update"]).is_none());
+
+    let message = descriptor.check_subcommand(&["foo"]);
+    assert!(message.is_some());
+
+    let message = message.unwrap();
+    assert!(message.starts_with("--> unknown subcommand [ foo ] of [ dns ]\n"));
+    assert!(message.ends_with(&usage(descriptor)));
+
+    let message = descriptor.check_subcommand(&[]);
+    assert!(message.is_some());
+    assert!(message.unwrap().starts_with("--> missing subcommand of [ dns ]"));
+
+    let descriptor = search(COMMANDS, "dk").unwrap();
+    assert!(descriptor.check_subcommand(&["foo"]).is_none());
+
+    let descriptor = search(COMMANDS, "env").unwrap();
+    assert!(descriptor.check_subcommand(&[]).is_none());
+    assert!(descriptor.check_subcommand(&["set"]).is_none());
+    assert!(descriptor.check_subcommand(&["foo"]).is_some());
+}
+
+#[test]
+fn test_usage() {
+    let descriptor = search(COMMANDS, "quit").unwrap();
+    let expect = "--> usage of [ quit ]:\n    \
+                  quit    Exit turtle shell.\n\
+                  --> aliases: exit";
+    assert_eq!(usage(descriptor), expect);
+
+    let descriptor = search(COMMANDS, "install").unwrap();
+    let expect = "--> usage of [ install ]:\n    \
+                  install brew                        Install Homebrew.\n    \
+                  install [package1] [package2] ..    \
+                  Install a list of packages using Homebrew.";
+    assert_eq!(usage(descriptor), expect);
+}
+
+#[test]
+fn test_custom_usage() {
+    let custom = sample_custom();
+    let expect = "--> usage of [ migrate ]:\n    \
+                  migrate {database}    Run database migrations.";
+    assert_eq!(custom_usage(&custom), expect);
+}
+
+#[test]
+fn test_help() {
+    let output = help(COMMANDS, &[sample_custom()]);
+    let lines: Vec<&str> = output.lines().collect();
+
+    assert_eq!(lines[0], "--> supported commands:");
+    assert!(lines[1].starts_with("    quit, exit    "));
+    assert!(lines[1].ends_with("Exit turtle shell."));
+    assert_eq!(lines.len(), COMMANDS.len() + 4);
+
+    let n = COMMANDS.len();
+    assert_eq!(lines[n + 1], "--> custom commands:");
+    assert!(lines[n + 2].starts_with("    migrate    "));
+    assert_eq!(lines[n + 3], "--> type help {command} for more details");
+}
+
+#[test]
+fn test_markdown_table_in_readme() {
+    let readme = fs::read_to_string("README.md").expect("cannot read readme");
+    let table = markdown_table(COMMANDS);
+    assert!(readme.contains(&table));
+}
@@ -1,105 +1,329 @@
 #[cfg(test)]
 mod test;
 
-use super::cmd::Command;
-use super::git;
-use super::instr::Instruction;
+use super::cmd::{Command, CommandResult, ErrorKind};
+use super::decr;
+use super::instr::{Instruction, Strategy};
 use super::util;
 
 use dirs;
-use subprocess::{ExitStatus, PopenError, Redirection::Pipe};
+use std::cmp;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use subprocess::{
+    Exec, ExitStatus, NullFile, Popen, PopenError,
+    Redirection::{Merge, Pipe},
+};
 use termion::{color, color::Magenta, color::Red, style};
 
-// Return current git branch of current directory if it is a git repository,
-// or empty string if it isn't
-pub fn current_git_branch() -> String {
-    let command = git::current_branch();
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+const KILL_GRACE_PERIOD: Duration = Duration::from_secs(2);
+const TIMEOUT_EXIT_CODE: i32 = 124;
+const CANCELLED_EXIT_CODE: i32 = 130;
 
-    let (success, branch) = run_command(&command);
-    if success {
-        return branch;
-    }
-
-    return String::new();
-}
+// Number of ctrl-c presses, counted by the ctrl-c handler and cleared when a
+// new instruction starts
+static INTERRUPTS: AtomicUsize = AtomicUsize::new(0);
 
-// Execute command as a child process and wait for it to finish,
-// return true and output string if success
-pub fn run_command(command: &Command) -> (bool, String) {
+// Execute command as a child process and wait for it to finish
+pub fn run_command(command: &Command) -> CommandResult {
     let back_dir = util::current_directory();
     if !change_directory(&command.dir, command.show) {
-        return (false, String::new());
+        let message = format!("cannot change directory to [ {} ]", command.dir);
+        return CommandResult::fail(ErrorKind::Directory, &message);
     }
 
-    let (mut success, mut stdout) = run_raw_command(command);
-    if success {
+    let mut result = run_raw_command(command);
+    if result.success() {
         if let Some(then) = &command.then {
-            let (ok, out) = then(&stdout);
-            success = ok;
-            stdout = out;
+            let next = then(&result.stdout);
+            if !next.success() && !command.silent {
+                report_error(&next);
+            }
+
+            result.stdout = next.stdout;
+            result.error = next.error;
+            if !next.stderr.is_empty() {
+                result.stderr = next.stderr;
+            }
         }
     }
 
     if command.back && !command.dir.is_empty() {
         if !change_directory(&back_dir, command.show) {
-            return (false, String::new());
+            let message = format!("cannot change directory to [ {} ]", back_dir);
+            return CommandResult::fail(ErrorKind::Directory, &message);
         }
     }
 
-    return (success, stdout);
+    return result;
 }
 
-pub fn run_raw_command(command: &Command) -> (bool, String) {
+pub fn run_raw_command(command: &Command) -> CommandResult {
     let raw = &command.raw;
     if raw.is_empty() {
-        return (true, String::new());
+        return CommandResult::ok("");
     }
 
     if command.show {
         println!("{}$ {}{}", color::Fg(Magenta), raw, style::Reset);
     }
 
-    let mut exec_error: Option<PopenError> = None;
-    let mut exit_status = ExitStatus::Exited(0);
+    let start = Instant::now();
 
+    let mut exec = with_env(subprocess::Exec::shell(raw), &command.env);
     if command.pipe {
-        let exec = subprocess::Exec::shell(raw).stdout(Pipe).stderr(Pipe);
+        exec = exec.stdout(Pipe).stderr(Pipe);
+    }
+
+    let mut result = match exec.popen() {
+        Ok(mut popen) => {
+            let stdout = popen.stdout.take();
+            let stderr = popen.stderr.take();
+
+            thread::scope(|scope| {
+                let out = scope.spawn(|| read_output(stdout));
+                let err = scope.spawn(|| read_output(stderr));
+
+                let mut result =
+                    wait_child(&mut popen, command.timeout, &INTERRUPTS);
+                result.stdout = out.join().unwrap_or_default();
+                let stderr = err.join().unwrap_or_default();
+                if result.stderr.is_empty() {
+                    result.stderr = stderr;
+                }
+                result
+            })
+        }
+        Err(err) => execute_error(err),
+    };
+
+    result.duration = start.elapsed();
+
+    if !command.silent {
+        report_error(&result);
+    }
+
+    return result;
+}
 
-        match exec.capture() {
-            Ok(data) => {
-                if data.success() {
-                    let stdout = data.stdout_str();
-                    return (true, stdout);
+// Executes all commands following the strategy of the instruction, return
+// the first failure, or the result of the last command if all of them
+// succeed. Commands are only printed in dry-run mode. An invalid instruction
+// always fails, after its error message is printed
+pub fn run_instruction(instruction: &Instruction) -> CommandResult {
+    let result = if instruction.dry_run {
+        print_instruction(instruction);
+        CommandResult::ok("")
+    } else {
+        run_commands(instruction)
+    };
+
+    if instruction.error && result.success() {
+        return CommandResult::fail(ErrorKind::Invalid, "invalid instruction");
+    }
+    return result;
+}
+
+fn run_commands(instruction: &Instruction) -> CommandResult {
+    INTERRUPTS.store(0, Ordering::SeqCst);
+
+    let commands = &instruction.commands;
+    let results: Vec<CommandResult> = match instruction.strategy {
+        Strategy::Sequential => {
+            let mut result = CommandResult::ok("");
+            for cmd in commands {
+                result = run_command(cmd);
+                if !result.success() {
+                    return result;
+                }
+            }
+            return result;
+        }
+        Strategy::ContinueOnError => commands
+            .iter()
+            .map(|cmd| {
+                if is_cancelled() {
+                    return cancelled_result();
+                }
+                return run_command(cmd);
+            })
+            .collect(),
+        Strategy::Parallel(max) => run_parallel(commands, max),
+    };
+
+    print_summary(commands, &results);
+
+    for result in &results {
+        if !result.success() {
+            return result.clone();
+        }
+    }
+
+    match results.last() {
+        Some(result) => return result.clone(),
+        None => return CommandResult::ok(""),
+    }
+}
+
+// Run commands concurrently, at most max commands at the same time, return
+// result of each of them
+pub fn run_parallel(commands: &[Command], max: usize) -> Vec<CommandResult> {
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(vec![CommandResult::ok(""); commands.len()]);
+    let workers = cmp::max(1, cmp::min(max, commands.len()));
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::SeqCst);
+                if i >= commands.len() {
+                    break;
+                }
+
+                let result = if is_cancelled() {
+                    cancelled_result()
                 } else {
-                    exit_status = data.exit_status;
-                    if !command.silent {
-                        println!("{}", data.stderr_str());
+                    run_prefixed_command(&commands[i])
+                };
+                results.lock().unwrap()[i] = result;
+            });
+        }
+    });
+
+    return results.into_inner().unwrap();
+}
+
+// Execute command in its directory without changing current directory,
+// print each line of its output prefixed by its label
+pub fn run_prefixed_command(command: &Command) -> CommandResult {
+    let raw = &command.raw;
+    if raw.is_empty() {
+        return CommandResult::ok("");
+    }
+
+    let prefix = if command.label.is_empty() {
+        String::new()
+    } else {
+        format!("{} ", decr::yellow(&format!("[{}]", command.label)))
+    };
+
+    if command.show {
+        println!("{}{}$ {}{}", prefix, color::Fg(Magenta), raw, style::Reset);
+    }
+
+    let start = Instant::now();
+
+    let mut exec = subprocess::Exec::shell(raw).stdout(Pipe).stderr(Merge);
+    exec = with_env(exec, &command.env);
+    if !command.dir.is_empty() {
+        exec = exec.cwd(util::normalize_path(&command.dir));
+    }
+
+    let mut popen = match exec.popen() {
+        Ok(popen) => popen,
+        Err(err) => {
+            println!("{}{}", prefix, decr::red(&format!("--> {}", err)));
+            return execute_error(err);
+        }
+    };
+
+    let stdout = popen.stdout.take();
+    let mut result = thread::scope(|scope| {
+        let printer = scope.spawn(|| {
+            let mut output: Vec<String> = Vec::new();
+            if let Some(stdout) = stdout {
+                for line in BufReader::new(stdout).lines() {
+                    match line {
+                        Ok(line) => {
+                            println!("{}{}", prefix, line);
+                            output.push(line);
+                        }
+                        Err(_) => break,
                     }
                 }
             }
-            Err(err) => exec_error = Some(err),
+            output
+        });
+
+        let mut result =
+            wait_child(&mut popen, command.timeout, &INTERRUPTS);
+        result.stdout = printer.join().unwrap_or_default().join("\n");
+        result
+    });
+    result.duration = start.elapsed();
+
+    if result.success() {
+        if let Some(then) = &command.then {
+            let next = then(&result.stdout);
+            result.stdout = next.stdout;
+            result.stderr = next.stderr;
+            result.error = next.error;
         }
-    } else {
-        let result = subprocess::Exec::shell(raw).join();
-        match result {
-            Ok(status) => exit_status = status,
-            Err(err) => exec_error = Some(err),
+    }
+
+    return result;
+}
+
+// Print the result and duration of each labeled command
+pub fn print_summary(commands: &[Command], results: &[CommandResult]) {
+    let mut lines: Vec<String> = Vec::new();
+
+    for (cmd, result) in commands.iter().zip(results.iter()) {
+        if cmd.label.is_empty() {
+            continue;
         }
+
+        let mark = if result.success() {
+            decr::green("✓")
+        } else {
+            decr::red("𐄂")
+        };
+        let seconds = result.duration.as_secs_f64();
+        let status = match result.error {
+            Some(ErrorKind::Timeout) => " timed out",
+            Some(ErrorKind::Cancelled) => " cancelled",
+            _ => "",
+        };
+        lines.push(format!(
+            "    {} {} ({:.1}s){}",
+            mark, cmd.label, seconds, status
+        ));
     }
 
-    return handle_error(command.silent, exec_error, exit_status);
+    if !lines.is_empty() {
+        println!("--> summary:\n{}", lines.join("\n"));
+    }
 }
 
-// Executes all commands sequentially, stop immediately in case of failure,
-// return true if success
-pub fn run_instruction(instruction: &Instruction) -> bool {
+// Print what each command of an instruction would do without running it,
+// messages of echo commands are printed as they are
+pub fn print_instruction(instruction: &Instruction) {
+    let commands = &instruction.commands;
+    let total = commands.iter().filter(|cmd| cmd.message.is_none()).count();
+    let strategy = match instruction.strategy {
+        Strategy::Sequential => String::from("sequentially"),
+        Strategy::ContinueOnError => String::from("continue on error"),
+        Strategy::Parallel(max) => format!("{} at a time", max),
+    };
+    if total > 0 {
+        println!(
+            "{}--> dry-run: {} command(s), {}{}",
+            color::Fg(Magenta),
+            total,
+            strategy,
+            style::Reset
+        );
+    }
+
     for cmd in &instruction.commands {
-        let (success, _) = run_command(cmd);
-        if !success {
-            return false;
-        }
+        println!("{}", cmd.render());
     }
-    return true;
 }
 
 pub fn change_directory(dir: &str, show: bool) -> bool {
@@ -123,28 +347,178 @@ pub fn change_directory(dir: &str, show: bool) -> bool {
     return true;
 }
 
-fn handle_error(silent: bool, error: Option<PopenError>, status: ExitStatus) -> (bool, String) {
-    if !silent {
-        if let Some(err) = error {
-            println!(
-                "{}--> execute error: {}{}",
-                color::Fg(Red),
-                err,
-                style::Reset
-            );
-            return (false, String::new());
+// Ask the running commands to stop, called when user presses ctrl-c
+pub fn cancel() {
+    INTERRUPTS.fetch_add(1, Ordering::SeqCst);
+}
+
+pub fn is_cancelled() -> bool {
+    return INTERRUPTS.load(Ordering::SeqCst) > 0;
+}
+
+// Wait for a child process to finish, kill it and all of its descendants
+// when it runs longer than the timeout. The first ctrl-c only interrupts the
+// child, which may handle it and go on, the second one kills it
+fn wait_child(
+    popen: &mut Popen,
+    timeout: Option<Duration>,
+    interrupts: &AtomicUsize,
+) -> CommandResult {
+    let start = Instant::now();
+    let mut interrupted = false;
+
+    loop {
+        match popen.wait_timeout(POLL_INTERVAL) {
+            Ok(Some(status)) => return exit_result(status),
+            Ok(None) => (),
+            Err(err) => return execute_error(err),
         }
 
-        if !status.success() {
-            println!(
-                "{}--> failed with exit status = {:?}{}",
-                color::Fg(Red),
-                status,
-                style::Reset,
-            );
-            return (false, String::new());
+        match interrupts.load(Ordering::SeqCst) {
+            0 => (),
+            1 if !interrupted => {
+                interrupted = true;
+                interrupt_child(popen);
+            }
+            1 => (),
+            _ => {
+                kill_child(popen);
+                return cancelled_result();
+            }
+        }
+
+        if let Some(limit) = timeout {
+            if start.elapsed() >= limit {
+                kill_child(popen);
+                let message =
+                    format!("timed out after {} seconds", limit.as_secs());
+                let mut result =
+                    CommandResult::fail(ErrorKind::Timeout, &message);
+                result.code = Some(TIMEOUT_EXIT_CODE);
+                return result;
+            }
+        }
+    }
+}
+
+// At a terminal ctrl-c already reaches the child, which runs in the
+// foreground process group of turtle, otherwise pass it on to the whole
+// process tree of the child
+fn interrupt_child(popen: &mut Popen) {
+    if termion::is_tty(&io::stdin()) {
+        return;
+    }
+    if let Some(pid) = popen.pid() {
+        signal_processes(&process_tree(pid), "INT");
+    }
+}
+
+// Terminate a child process with its whole process tree, a shell command
+// such as `a | b` has several processes that must all be stopped. Those
+// still alive after a grace period are killed
+fn kill_child(popen: &mut Popen) {
+    let pids = match popen.pid() {
+        Some(pid) => process_tree(pid),
+        None => return,
+    };
+
+    signal_processes(&pids, "TERM");
+    let _ = popen.wait_timeout(KILL_GRACE_PERIOD);
+
+    signal_processes(&pids, "KILL");
+    let _ = popen.wait();
+}
+
+// Return the process id and the ids of all of its descendants
+fn process_tree(pid: u32) -> Vec<u32> {
+    let mut pids = vec![pid];
+
+    let raw = format!("pgrep -P {}", pid);
+    let exec = subprocess::Exec::shell(&raw).stdout(Pipe).stderr(NullFile);
+    if let Ok(data) = exec.capture() {
+        for child in data.stdout_str().split_whitespace() {
+            if let Ok(child) = child.parse::<u32>() {
+                pids.extend(process_tree(child));
+            }
         }
     }
 
-    return (true, String::new());
+    return pids;
+}
+
+fn signal_processes(pids: &[u32], signal: &str) {
+    let pids: Vec<String> = pids.iter().map(u32::to_string).collect();
+    let raw = format!("kill -{} {}", signal, pids.join(" "));
+    let exec = subprocess::Exec::shell(&raw).stdout(NullFile).stderr(NullFile);
+    let _ = exec.join();
+}
+
+fn with_env(exec: Exec, env: &BTreeMap<String, Option<String>>) -> Exec {
+    let mut exec = exec;
+    for (key, value) in env {
+        exec = match value {
+            Some(value) => exec.env(key, value),
+            None => exec.env_remove(key),
+        };
+    }
+    return exec;
+}
+
+fn read_output(file: Option<File>) -> String {
+    let mut output = String::new();
+    if let Some(mut file) = file {
+        let _ = file.read_to_string(&mut output);
+    }
+    return output;
+}
+
+fn cancelled_result() -> CommandResult {
+    let mut result = CommandResult::fail(ErrorKind::Cancelled, "cancelled");
+    result.code = Some(CANCELLED_EXIT_CODE);
+    return result;
+}
+
+fn exit_result(status: ExitStatus) -> CommandResult {
+    let code = match status {
+        ExitStatus::Exited(code) => Some(code as i32),
+        ExitStatus::Signaled(signal) => Some(128 + signal as i32),
+        ExitStatus::Other(code) => Some(code),
+        ExitStatus::Undetermined => None,
+    };
+
+    let mut result = if status.success() {
+        CommandResult::ok("")
+    } else {
+        CommandResult::fail(ErrorKind::Exit, "")
+    };
+    result.code = code;
+
+    return result;
+}
+
+fn execute_error(error: PopenError) -> CommandResult {
+    return CommandResult::fail(ErrorKind::Execute, &error.to_string());
+}
+
+fn report_error(result: &CommandResult) {
+    let message = match result.error {
+        None | Some(ErrorKind::Directory) | Some(ErrorKind::Invalid) => return,
+        Some(ErrorKind::Execute) => format!("execute error: {}", result.stderr),
+        Some(ErrorKind::Exit) => {
+            if !result.stderr.is_empty() {
+                println!("{}", result.stderr);
+            }
+
+            match result.code {
+                Some(code) => format!("failed with exit status = {}", code),
+                None => String::from("failed with unknown exit status"),
+            }
+        }
+        Some(ErrorKind::Output) => result.stderr.clone(),
+        Some(ErrorKind::Timeout) | Some(ErrorKind::Cancelled) => {
+            result.stderr.clone()
+        }
+    };
+
+    println!("{}--> {}{}", color::Fg(Red), message, style::Reset);
 }
@@ -2,9 +2,9 @@ use super::*;
 
 #[test]
 fn test_run_command_pipe() {
-    let exec = |name: &str| -> (bool, String) {
+    let exec = |name: &str| -> CommandResult {
         let s = format!("hello {}", name);
-        return (true, s);
+        return CommandResult::ok(&s);
     };
 
     let command = Command::new(
@@ -18,10 +18,11 @@ fn test_run_command_pipe() {
     );
 
     let prev_dir = util::current_directory();
-    let (success, output) = run_command(&command);
+    let result = run_command(&command);
 
-    assert!(success);
-    assert_eq!(output, "hello julia\n");
+    assert!(result.success());
+    assert_eq!(result.code, Some(0));
+    assert_eq!(result.stdout, "hello julia\n");
 
     let back_dir = util::current_directory();
     assert_ne!(back_dir, prev_dir);
@@ -32,9 +33,9 @@ fn test_run_command_pipe() {
 
 #[test]
 fn test_run_command_no_pipe() {
-    let exec = |name: &str| -> (bool, String) {
+    let exec = |name: &str| -> CommandResult {
         let s = format!("name = [{}]", name);
-        return (true, s);
+        return CommandResult::ok(&s);
     };
 
     let command = Command::new(
@@ -48,19 +49,185 @@ fn test_run_command_no_pipe() {
     );
 
     let prev_dir = util::current_directory();
-    let (success, output) = run_command(&command);
+    let result = run_command(&command);
 
-    assert!(success);
-    assert_eq!(output, "name = []");
+    assert!(result.success());
+    assert_eq!(result.stdout, "name = []");
 
     let back_dir = util::current_directory();
     assert_eq!(back_dir, prev_dir);
 }
 
+#[test]
+fn test_run_command_failures() {
+    let command = Command::new("ls", "unknown", false, true, false, None, true);
+    let result = run_command(&command);
+    assert_eq!(result.error, Some(ErrorKind::Directory));
+
+    let command =
+        Command::new("echo oops >&2; exit 3", "", false, true, true, None, true);
+    let result = run_command(&command);
+    assert_eq!(result.error, Some(ErrorKind::Exit));
+    assert_eq!(result.code, Some(3));
+    assert_eq!(result.stderr, "oops\n");
+
+    let exec = |_: &str| -> CommandResult {
+        return CommandResult::fail(ErrorKind::Output, "unexpected output");
+    };
+    let command =
+        Command::new("true", "", false, true, true, Some(Box::new(exec)), true);
+    let result = run_command(&command);
+    assert_eq!(result.error, Some(ErrorKind::Output));
+    assert_eq!(result.code, Some(0));
+    assert_eq!(result.stderr, "unexpected output");
+}
+
 #[test]
 fn test_run_instruction() {
     let command = Command::basic_show("ls -la");
     let instruction = Instruction::basic(vec![command]);
-    let success = run_instruction(&instruction);
-    assert!(success);
+    let result = run_instruction(&instruction);
+    assert!(result.success());
+}
+
+#[test]
+fn test_run_instruction_dry_run() {
+    let command = Command::basic_show("false");
+    let mut instruction = Instruction::basic(vec![command]);
+    assert!(!run_instruction(&instruction).success());
+
+    instruction.dry_run = true;
+    assert!(run_instruction(&instruction).success());
+}
+
+#[test]
+fn test_run_instruction_error() {
+    let mut instruction = Instruction::error("--> unknown group [ abc ]");
+    let result = run_instruction(&instruction);
+    assert_eq!(result.error, Some(ErrorKind::Invalid));
+    assert_eq!(result.exit_code(), 1);
+
+    instruction.dry_run = true;
+    assert_eq!(run_instruction(&instruction).exit_code(), 1);
+}
+
+#[test]
+fn test_run_instruction_continue_on_error() {
+    let commands = vec![
+        Command::basic_hide("exit 2").labeled("lotus"),
+        Command::basic_hide("true").labeled("camellia"),
+    ];
+    let instruction =
+        Instruction::with_strategy(commands, Strategy::ContinueOnError);
+
+    let result = run_instruction(&instruction);
+    assert_eq!(result.error, Some(ErrorKind::Exit));
+    assert_eq!(result.code, Some(2));
+}
+
+#[test]
+fn test_run_parallel() {
+    let commands = vec![
+        Command::basic_hide("echo lotus").labeled("lotus"),
+        Command::basic_hide("exit 3").labeled("camellia"),
+        Command::new("pwd", "src", true, false, false, None, true),
+    ];
+
+    let prev_dir = util::current_directory();
+    let results = run_parallel(&commands, 2);
+    let success: Vec<bool> = results.iter().map(|r| r.success()).collect();
+    assert_eq!(success, vec![true, false, true]);
+    assert_eq!(results[0].stdout, "lotus");
+    assert_eq!(results[1].code, Some(3));
+    assert!(results[2].stdout.ends_with("src"));
+    assert_eq!(util::current_directory(), prev_dir);
+
+    let instruction =
+        Instruction::with_strategy(commands, Strategy::Parallel(8));
+    assert!(!run_instruction(&instruction).success());
+}
+
+#[test]
+fn test_run_prefixed_command() {
+    let exec = |output: &str| -> CommandResult {
+        if output == "hello\nworld" {
+            return CommandResult::ok(output);
+        }
+        return CommandResult::fail(ErrorKind::Output, output);
+    };
+    let command = Command::new(
+        "echo hello && echo world >&2",
+        "",
+        false,
+        false,
+        false,
+        Some(Box::new(exec)),
+        true,
+    );
+    assert!(run_prefixed_command(&command).success());
+
+    let command = Command::basic_hide("true").labeled("lotus");
+    assert!(run_prefixed_command(&command).success());
+
+    let command =
+        Command::new("true", "unknown", false, false, false, None, true);
+    let result = run_prefixed_command(&command);
+    assert_eq!(result.error, Some(ErrorKind::Execute));
+}
+
+#[test]
+fn test_run_command_timeout() {
+    let command =
+        Command::new("sleep 30 | cat", "", false, true, true, None, true)
+            .with_timeout(Some(1));
+
+    let start = Instant::now();
+    let result = run_command(&command);
+
+    assert_eq!(result.error, Some(ErrorKind::Timeout));
+    assert_eq!(result.code, Some(124));
+    assert_eq!(result.stderr, "timed out after 1 seconds");
+    assert!(start.elapsed() < Duration::from_secs(10));
+}
+
+#[test]
+fn test_wait_child_interrupted() {
+    // The first ctrl-c lets the child finish, the second one kills it
+    let interrupts = AtomicUsize::new(1);
+    let raw = "trap '' INT; sleep 1; exit 3";
+    let mut popen = Exec::shell(raw).popen().unwrap();
+    let result = wait_child(&mut popen, None, &interrupts);
+    assert_eq!(result.code, Some(3));
+
+    let mut popen = Exec::shell("trap '' INT; sleep 30").popen().unwrap();
+    let start = Instant::now();
+    let result = thread::scope(|scope| {
+        scope.spawn(|| {
+            thread::sleep(Duration::from_millis(500));
+            interrupts.store(2, Ordering::SeqCst);
+        });
+        return wait_child(&mut popen, None, &interrupts);
+    });
+
+    assert_eq!(result.error, Some(ErrorKind::Cancelled));
+    assert!(start.elapsed() < Duration::from_secs(10));
+}
+
+#[test]
+fn test_run_command_env() {
+    let mut env = BTreeMap::new();
+    env.insert(String::from("TURTLE_FLOWER"), Some(String::from("lotus")));
+    env.insert(String::from("HOME"), None);
+
+    let raw = "echo \"$TURTLE_FLOWER:${HOME:-none}\"";
+    let command =
+        Command::new(raw, "", false, true, true, None, true).with_env(&env);
+    let result = run_command(&command);
+    assert!(result.success());
+    assert_eq!(result.stdout, "lotus:none\n");
+
+    let command = Command::basic_hide(raw).with_env(&env).labeled("lotus");
+    let result = run_prefixed_command(&command);
+    assert!(result.success());
+    assert_eq!(result.stdout, "lotus:none");
 }
@@ -2,12 +2,14 @@
 mod test;
 
 use std::env;
+use std::fs;
 use std::io;
-use std::path::{Path, MAIN_SEPARATOR};
+use std::path::{Path, PathBuf, MAIN_SEPARATOR};
 
 use super::config::Shortcut;
 
 const TILDE: &str = "~";
+const CONFIG_FILE_SUFFIX: &str = ".config.toml";
 
 pub fn home_directory() -> String {
     if let Some(pb) = dirs::home_dir() {
@@ -27,7 +29,25 @@ pub fn default_config_directory() -> String {
 }
 
 pub fn config_file(dir: &str, project: &str) -> String {
-    return format!("{}/{}.config.toml", dir, project);
+    return format!("{}/{}{}", dir, project, CONFIG_FILE_SUFFIX);
+}
+
+// Return sorted name of all projects having a config file in a directory
+pub fn list_projects(dir: &str) -> io::Result<Vec<String>> {
+    let mut projects: Vec<String> = Vec::new();
+
+    for entry in fs::read_dir(dir)? {
+        let name = entry?.file_name();
+        if let Some(name) = name.to_str() {
+            if name.ends_with(CONFIG_FILE_SUFFIX) {
+                let project = name.trim_end_matches(CONFIG_FILE_SUFFIX);
+                projects.push(String::from(project));
+            }
+        }
+    }
+
+    projects.sort();
+    return Ok(projects);
 }
 
 pub fn compose_file(dir: &str, project: &str) -> String {
@@ -99,6 +119,42 @@ pub fn normalize_path(path: &str) -> String {
     return String::from(path);
 }
 
+// Return all instructions of a script file, ignoring empty lines and comments
+pub fn load_script(file_path: &str) -> io::Result<Vec<String>> {
+    let text = fs::read_to_string(file_path)?;
+    let lines = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect();
+    return Ok(lines);
+}
+
+// Return the git directory of the repository containing dir, following the
+// `gitdir:` link of worktrees and submodules, or None if dir isn't in a
+// git repository
+pub fn find_git_directory(dir: &str) -> Option<PathBuf> {
+    let mut current = Some(Path::new(dir));
+
+    while let Some(dir) = current {
+        let git = dir.join(".git");
+        if git.is_dir() {
+            return Some(git);
+        }
+
+        if git.is_file() {
+            let text = fs::read_to_string(&git).ok()?;
+            let link = text.trim().trim_start_matches("gitdir:").trim();
+            return Some(dir.join(link));
+        }
+
+        current = dir.parent();
+    }
+
+    return None;
+}
+
 pub fn path_exist(path: &str) -> bool {
     return Path::new(path).exists();
 }
@@ -108,13 +164,304 @@ pub fn normalize_spaces(text: &str) -> String {
     return tokens.join(" ");
 }
 
-pub fn replace_shortcuts(text: &str, shortcuts: &[Shortcut]) -> String {
+// Expand the first shortcut whose prefix matches text. If the shortcut value
+// contains placeholders ($1, ${2:-default}, $@) they are filled with the
+// following arguments, the rest of text is appended as is, so it can still
+// contain shell syntax such as pipes and redirections
+pub fn replace_shortcuts(
+    text: &str,
+    shortcuts: &[Shortcut],
+) -> Result<String, String> {
     for shortcut in shortcuts {
         for prefix in &shortcut.prefixes {
-            if text.starts_with(prefix) {
-                return text.replacen(prefix, &shortcut.value, 1);
+            let rest = match strip_prefix_words(text, prefix) {
+                Some(rest) => rest,
+                None => continue,
+            };
+
+            if !shortcut.value.contains('$') {
+                return Ok(format!("{}{}", shortcut.value, rest));
+            }
+
+            let words = match split_words(rest) {
+                Ok(words) => words,
+                Err(err) => {
+                    return Err(format!(
+                        "--> cannot parse arguments of shortcut [ {} ]: {}",
+                        prefix, err
+                    ));
+                }
+            };
+
+            let args: Vec<String> =
+                words.iter().map(|(word, _)| word.to_owned()).collect();
+            match fill_placeholders(&shortcut.value, &args) {
+                Ok((mut result, used)) => {
+                    let end = if used > 0 { words[used - 1].1 } else { 0 };
+                    result.push_str(&rest[end..]);
+                    return Ok(result);
+                }
+                Err(placeholder) => {
+                    return Err(format!(
+                        "--> missing argument [ {} ] for shortcut [ {} ]",
+                        placeholder, prefix
+                    ));
+                }
+            }
+        }
+    }
+    return Ok(String::from(text));
+}
+
+// Return the rest of text after the words of a prefix, which may be separated
+// by any number of spaces in text, or None if text doesn't start with them
+fn strip_prefix_words<'a>(text: &'a str, prefix: &str) -> Option<&'a str> {
+    let mut rest = text.trim_start();
+    for word in prefix.split_whitespace() {
+        rest = rest.trim_start();
+        if !rest.starts_with(word) {
+            return None;
+        }
+        rest = &rest[word.len()..];
+    }
+
+    if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    return Some(rest);
+}
+
+// Split text into arguments like a shell does, respecting single quotes,
+// double quotes and backslash escapes, and return where each argument ends in
+// text. Splitting stops at the first shell operator such as | > & or ; that
+// is not quoted
+fn split_words(text: &str) -> Result<Vec<(String, usize)>, String> {
+    let mut words: Vec<(String, usize)> = Vec::new();
+    let mut current = String::new();
+    let mut started = false;
+    let mut quote: Option<char> = None;
+    let mut chars = text.char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        match quote {
+            Some(q) => {
+                if c == q {
+                    quote = None;
+                } else if c == '\\' && q == '"' {
+                    match chars.next() {
+                        Some((_, n)) => current.push(n),
+                        None => return Err(String::from("unterminated quote")),
+                    }
+                } else {
+                    current.push(c);
+                }
+            }
+            None => {
+                if c.is_whitespace() || "|&;<>".contains(c) {
+                    if started {
+                        words.push((current, i));
+                        current = String::new();
+                        started = false;
+                    }
+                    if c.is_whitespace() {
+                        continue;
+                    }
+                    return Ok(words);
+                }
+
+                started = true;
+                if c == '\'' || c == '"' {
+                    quote = Some(c);
+                } else if c == '\\' {
+                    if let Some((_, n)) = chars.next() {
+                        current.push(n);
+                    }
+                } else {
+                    current.push(c);
+                }
+            }
+        }
+    }
+
+    if quote.is_some() {
+        return Err(String::from("unterminated quote"));
+    }
+
+    if started {
+        words.push((current, text.len()));
+    }
+
+    return Ok(words);
+}
+
+// Split text on whitespace that is not quoted or escaped, each word keeps its
+// quotes, so joining the words with spaces gives back the same shell text
+pub fn split_shell_words(text: &str) -> Vec<&str> {
+    let mut words: Vec<&str> = Vec::new();
+    let mut start: Option<usize> = None;
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+
+    for (i, c) in text.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+
+        match quote {
+            Some(q) => {
+                if c == q {
+                    quote = None;
+                } else if c == '\\' && q == '"' {
+                    escaped = true;
+                }
+            }
+            None => {
+                if c.is_whitespace() {
+                    if let Some(s) = start.take() {
+                        words.push(&text[s..i]);
+                    }
+                    continue;
+                }
+
+                if start.is_none() {
+                    start = Some(i);
+                }
+                if c == '\'' || c == '"' {
+                    quote = Some(c);
+                } else if c == '\\' {
+                    escaped = true;
+                }
             }
         }
     }
-    return String::from(text);
+
+    if let Some(s) = start {
+        words.push(&text[s..]);
+    }
+    return words;
+}
+
+// Remove quotes and backslash escapes of a shell word
+pub fn unquote(word: &str) -> String {
+    let mut result = String::new();
+    let mut quote: Option<char> = None;
+    let mut chars = word.chars();
+
+    while let Some(c) = chars.next() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some('"') if c == '\\' => result.extend(chars.next()),
+            Some(_) => result.push(c),
+            None if c == '\'' || c == '"' => quote = Some(c),
+            None if c == '\\' => result.extend(chars.next()),
+            None => result.push(c),
+        }
+    }
+
+    return result;
+}
+
+// Quote an argument with single quotes if it contains any character that
+// has special meaning to the shell
+pub fn quote_argument(arg: &str) -> String {
+    let safe = |c: char| c.is_alphanumeric() || "-_./:=@,+%".contains(c);
+    if !arg.is_empty() && arg.chars().all(safe) {
+        return String::from(arg);
+    }
+    return format!("'{}'", arg.replace("'", "'\\''"));
+}
+
+// Replace $1, ${1}, ${1:-default} and $@ placeholders in text with quoted
+// arguments, return the filled text and how many leading arguments were
+// consumed, or the first placeholder that has no value
+pub fn fill_placeholders(
+    text: &str,
+    args: &[String],
+) -> Result<(String, usize), String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut result = String::new();
+    let mut used = 0;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).cloned();
+
+        if c != '$' || next.is_none() {
+            result.push(c);
+            i += 1;
+            continue;
+        }
+
+        match next.unwrap() {
+            '@' => {
+                let quoted: Vec<String> =
+                    args.iter().map(|a| quote_argument(a)).collect();
+                result.push_str(&quoted.join(" "));
+                used = args.len();
+                i += 2;
+            }
+            '1'..='9' => {
+                let mut j = i + 1;
+                while j < chars.len() && chars[j].is_ascii_digit() {
+                    j += 1;
+                }
+
+                let digits: String = chars[i + 1..j].iter().collect();
+                let index: usize = digits.parse().unwrap_or(0);
+                match args.get(index - 1) {
+                    Some(arg) => result.push_str(&quote_argument(arg)),
+                    None => return Err(format!("${}", index)),
+                }
+
+                used = used.max(index);
+                i = j;
+            }
+            '{' => {
+                let end = chars[i..].iter().position(|&c| c == '}');
+                let inner: String = match end {
+                    Some(end) => chars[i + 2..i + end].iter().collect(),
+                    None => String::new(),
+                };
+
+                let (digits, default) = match inner.find(":-") {
+                    Some(pos) => (&inner[..pos], Some(&inner[pos + 2..])),
+                    None => (&inner[..], None),
+                };
+
+                let index: usize = digits.parse().unwrap_or(0);
+                if end.is_none() || index == 0 {
+                    result.push(c);
+                    i += 1;
+                    continue;
+                }
+
+                match (args.get(index - 1), default) {
+                    (Some(arg), _) => result.push_str(&quote_argument(arg)),
+                    (None, Some(default)) => result.push_str(default),
+                    (None, None) => return Err(format!("${{{}}}", index)),
+                }
+
+                used = used.max(index);
+                i += end.unwrap() + 1;
+            }
+            _ => {
+                result.push(c);
+                i += 1;
+            }
+        }
+    }
+
+    return Ok((result, used.min(args.len())));
+}
+
+// Replace each {name} placeholder in text with its corresponding value
+pub fn fill_arguments(text: &str, names: &[&str], values: &[&str]) -> String {
+    let mut result = text.to_owned();
+    for (name, value) in names.iter().zip(values.iter()) {
+        let placeholder = format!("{{{}}}", name);
+        result = result.replace(&placeholder, value);
+    }
+    return result;
 }
@@ -20,6 +20,15 @@ fn test_config_file() {
     assert_eq!(file, expect);
 }
 
+#[test]
+fn test_list_projects() {
+    let projects = list_projects("etc").expect("cannot list projects");
+    assert!(projects.contains(&String::from("sample")));
+
+    let result = list_projects("unknown");
+    assert!(result.is_err());
+}
+
 #[test]
 fn test_compose_file() {
     let file = compose_file("/tmp", "turtle");
@@ -84,6 +93,16 @@ fn test_normalize_path() {
     assert_eq!(normalize_path(path), expect);
 }
 
+#[test]
+fn test_load_script() {
+    let lines = load_script("etc/sample.turtle").expect("cannot load script");
+    let expect = vec!["use dep", "start", "status"];
+    assert_eq!(lines, expect);
+
+    let result = load_script("unknown.turtle");
+    assert!(result.is_err());
+}
+
 #[test]
 fn test_path_exist() {
     assert!(path_exist("src/config/test.rs"));
@@ -110,5 +129,144 @@ fn test_replace_shortcuts() {
     };
 
     let output = replace_shortcuts(text, &[shortcut]);
+    assert_eq!(output, Ok(String::from(expect)));
+}
+
+#[test]
+fn test_replace_shortcuts_whole_word() {
+    let shortcuts = vec![Shortcut {
+        value: String::from("goto"),
+        prefixes: vec![String::from("go to"), String::from("gt")],
+    }];
+
+    let output = replace_shortcuts("gtx lotus", &shortcuts);
+    assert_eq!(output, Ok(String::from("gtx lotus")));
+
+    let output = replace_shortcuts("go   to lotus", &shortcuts);
+    assert_eq!(output, Ok(String::from("goto lotus")));
+}
+
+#[test]
+fn test_replace_shortcuts_placeholders() {
+    let shortcut = Shortcut {
+        value: String::from("psql -h localhost -U ${2:-dev} -d $1"),
+        prefixes: vec![String::from("db")],
+    };
+    let shortcuts = [shortcut];
+
+    let output = replace_shortcuts("db turtle", &shortcuts);
+    let expect = "psql -h localhost -U dev -d turtle";
+    assert_eq!(output, Ok(String::from(expect)));
+
+    let output = replace_shortcuts("db turtle admin -c 'select 1'", &shortcuts);
+    let expect = "psql -h localhost -U admin -d turtle -c 'select 1'";
+    assert_eq!(output, Ok(String::from(expect)));
+
+    let output = replace_shortcuts(r#"db "my  db" john | less"#, &shortcuts);
+    let expect = "psql -h localhost -U john -d 'my  db' | less";
+    assert_eq!(output, Ok(String::from(expect)));
+
+    let text = "db turtle > out.txt && cat out.txt";
+    let output = replace_shortcuts(text, &shortcuts);
+    let expect = "psql -h localhost -U dev -d turtle > out.txt && cat out.txt";
+    assert_eq!(output, Ok(String::from(expect)));
+
+    let output = replace_shortcuts("db", &shortcuts);
+    let expect = "--> missing argument [ $1 ] for shortcut [ db ]";
+    assert_eq!(output, Err(String::from(expect)));
+
+    let output = replace_shortcuts("db 'turtle", &shortcuts);
+    let expect = "--> cannot parse arguments of shortcut [ db ]: \
+                  unterminated quote";
+    assert_eq!(output, Err(String::from(expect)));
+}
+
+#[test]
+fn test_split_words() {
+    let words = |text: &str| -> Result<Vec<String>, String> {
+        let words = split_words(text)?;
+        return Ok(words.into_iter().map(|(word, _)| word).collect());
+    };
+
+    let output = words(r#"a "b c" 'd \e' f\ g "h\"i" ''"#);
+    let expect = vec!["a", "b c", "d \\e", "f g", "h\"i", ""];
+    assert_eq!(output, Ok(expect.iter().map(|s| s.to_string()).collect()));
+
+    let output = words("a 'b | c' | d > e");
+    let expect = vec!["a", "b | c"];
+    assert_eq!(output, Ok(expect.iter().map(|s| s.to_string()).collect()));
+
+    let output = words("a \"b");
+    assert_eq!(output, Err(String::from("unterminated quote")));
+}
+
+#[test]
+fn test_split_shell_words() {
+    let output = split_shell_words(r#"  dkcp exec "my  db" 'a b'\ c d\"e "#);
+    let expect = vec!["dkcp", "exec", "\"my  db\"", "'a b'\\ c", "d\\\"e"];
     assert_eq!(output, expect);
 }
+
+#[test]
+fn test_unquote() {
+    assert_eq!(unquote("lotus"), "lotus");
+    assert_eq!(unquote(r#""my  db""#), "my  db");
+    assert_eq!(unquote(r#"'a "b"'\ c"#), "a \"b\" c");
+    assert_eq!(unquote(r#""it\"s""#), "it\"s");
+}
+
+#[test]
+fn test_quote_argument() {
+    assert_eq!(quote_argument("lotus"), "lotus");
+    assert_eq!(quote_argument("key=value"), "key=value");
+    assert_eq!(quote_argument("select 1"), "'select 1'");
+    assert_eq!(quote_argument("it's"), "'it'\\''s'");
+    assert_eq!(quote_argument(""), "''");
+}
+
+#[test]
+fn test_fill_placeholders() {
+    let args = vec![String::from("one"), String::from("two words")];
+
+    let output = fill_placeholders("echo $2 $1 $1", &args);
+    assert_eq!(output, Ok((String::from("echo 'two words' one one"), 2)));
+
+    let output = fill_placeholders("echo ${1} ${3:-three} $HOME", &args);
+    let expect = String::from("echo one three $HOME");
+    assert_eq!(output, Ok((expect, 2)));
+
+    let output = fill_placeholders("echo $@", &args);
+    assert_eq!(output, Ok((String::from("echo one 'two words'"), 2)));
+
+    let output = fill_placeholders("echo ${3}", &args);
+    assert_eq!(output, Err(String::from("${3}")));
+}
+
+#[test]
+fn test_fill_arguments() {
+    let text = "psql -U {user} -d {db} -c 'select 1'";
+    let expect = "psql -U admin -d turtle -c 'select 1'";
+
+    let output = fill_arguments(text, &["user", "db"], &["admin", "turtle"]);
+    assert_eq!(output, expect);
+}
+
+#[test]
+fn test_find_git_directory() {
+    let test_dir = "etc/find-git";
+    fs::create_dir_all(format!("{}/repo/.git", test_dir)).unwrap();
+    fs::create_dir_all(format!("{}/repo/src/bin", test_dir)).unwrap();
+    fs::create_dir_all(format!("{}/worktree", test_dir)).unwrap();
+    fs::write(format!("{}/worktree/.git", test_dir), "gitdir: ../repo/.git\n")
+        .unwrap();
+
+    let found = find_git_directory(&format!("{}/repo/src/bin", test_dir));
+    let expect = Path::new(test_dir).join("repo").join(".git");
+    assert_eq!(found, Some(expect));
+
+    let found = find_git_directory(&format!("{}/worktree", test_dir));
+    let expect = Path::new(test_dir).join("worktree").join("../repo/.git");
+    assert_eq!(found, Some(expect));
+
+    fs::remove_dir_all(test_dir).expect("cannot remove test directory");
+}